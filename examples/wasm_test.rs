@@ -10,7 +10,7 @@ fn main() {
         console::error_1(&JsValue::from(format!("{}", e)))
     }));
 
-    Builder::new().create().enable_logger().unwrap();
+    let _handle = Builder::new().create().enable_logger().unwrap();
 
     log::info!("This is info");
     log::warn!("This is warn");
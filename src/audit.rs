@@ -0,0 +1,81 @@
+use std::{
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+///Hash the first line of a file is chained to
+const GENESIS: &str = "0000000000000000";
+
+///Result of verifying the hash chain of a log file with [`verify_hash_chain`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainStatus {
+    ///Every line is chained to the one before it, contains the number of lines
+    Intact(usize),
+    ///The line with this number, starting from 1, doesn't contain the hash of the line before it,
+    ///so it or the line before it was modified, inserted or removed
+    Broken(usize),
+}
+
+///Chain of line hashes of a log file
+pub(crate) struct HashChain {
+    previous: String,
+}
+
+impl HashChain {
+    ///Continues the chain of the file at `path`, or starts a new one if the file is empty
+    pub(crate) fn resume(path: &Path) -> Self {
+        let previous = std::fs::File::open(path)
+            .ok()
+            .and_then(|f| BufReader::new(f).lines().map_while(Result::ok).last())
+            .map_or_else(|| GENESIS.to_owned(), |line| hash(&line));
+        Self { previous }
+    }
+
+    ///Appends the hash of the previous line to every line of `text`
+    pub(crate) fn link(&mut self, text: &str) -> String {
+        let mut linked = String::with_capacity(text.len() + 18);
+        for line in text.lines() {
+            let line = format!("{line} #{}", self.previous);
+            self.previous = hash(&line);
+            linked.push_str(&line);
+            linked.push('\n');
+        }
+        linked
+    }
+}
+
+///First 8 bytes of the SHA-256 hash of the line, in hex
+fn hash(line: &str) -> String {
+    Sha256::digest(line.as_bytes())[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+///Verifies the hash chain of a log file written with
+///[`set_hash_chain`](crate::Logger::set_hash_chain)
+///
+///Every line has to end with the hash of the line before it, the first line with zeros. A file
+///that was rotated starts a new chain.
+///
+/// # Errors
+///
+/// returns an error if the file can't be read
+pub fn verify_hash_chain(path: &Path) -> std::io::Result<ChainStatus> {
+    let mut previous = GENESIS.to_owned();
+    let mut count = 0;
+
+    for line in BufReader::new(std::fs::File::open(path)?).lines() {
+        let line = line?;
+        count += 1;
+
+        match line.rsplit_once(" #") {
+            Some((_, linked)) if linked == previous => previous = hash(&line),
+            _ => return Ok(ChainStatus::Broken(count)),
+        }
+    }
+
+    Ok(ChainStatus::Intact(count))
+}
@@ -0,0 +1,128 @@
+use std::{
+    cell::Cell,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+thread_local! {
+    static IN_BATCH_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+enum Message<T> {
+    Item(T),
+    //Acknowledged once the batch is sent
+    Flush(Sender<()>),
+}
+
+///Collects items on a background thread and sends them in batches, once `max_items` are collected
+///or `interval` passes since the first item of the batch
+pub(crate) struct Batcher<T> {
+    sender: Mutex<Option<Sender<Message<T>>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<T: Send + 'static> Batcher<T> {
+    pub(crate) fn new(
+        max_items: usize,
+        interval: Duration,
+        mut send: impl FnMut(Vec<T>) + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = std::thread::spawn(move || {
+            IN_BATCH_THREAD.with(|b| b.set(true));
+            let mut batch = Vec::new();
+            let mut deadline: Option<Instant> = None;
+
+            loop {
+                let message = match deadline {
+                    Some(deadline) => {
+                        receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    }
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+
+                match message {
+                    Ok(Message::Item(item)) => {
+                        batch.push(item);
+                        deadline.get_or_insert_with(|| Instant::now() + interval);
+                        if batch.len() < max_items {
+                            continue;
+                        }
+                    }
+                    Ok(Message::Flush(ack)) => {
+                        if !batch.is_empty() {
+                            send(std::mem::take(&mut batch));
+                        }
+                        deadline = None;
+                        let _ = ack.send(());
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => {
+                        if !batch.is_empty() {
+                            send(batch);
+                        }
+                        return;
+                    }
+                }
+
+                send(std::mem::take(&mut batch));
+                deadline = None;
+            }
+        });
+
+        Self {
+            sender: Mutex::new(Some(sender)),
+            thread: Mutex::new(Some(thread)),
+        }
+    }
+
+    ///Adds an item to the batch
+    ///
+    ///Items pushed from the batch thread are dropped, so that records logged while sending a
+    ///batch, e.g. by the HTTP client, don't cause more batches to be sent
+    pub(crate) fn push(&self, item: T) {
+        if IN_BATCH_THREAD.with(Cell::get) {
+            return;
+        }
+
+        if let Some(sender) = &*self.sender.lock().unwrap() {
+            let _ = sender.send(Message::Item(item));
+        }
+    }
+
+    ///Sends the current batch and waits until it's sent
+    pub(crate) fn flush(&self) {
+        //Flushing from the batch thread would wait for itself
+        if IN_BATCH_THREAD.with(Cell::get) {
+            return;
+        }
+
+        let (ack, done) = mpsc::channel();
+        {
+            let sender = self.sender.lock().unwrap();
+            let Some(sender) = &*sender else {
+                return;
+            };
+            if sender.send(Message::Flush(ack)).is_err() {
+                return;
+            }
+        }
+        let _ = done.recv();
+    }
+}
+
+impl<T> Drop for Batcher<T> {
+    fn drop(&mut self) {
+        //Disconnecting makes the thread send the last batch and stop
+        self.sender.lock().unwrap().take();
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+}
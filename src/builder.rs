@@ -1,4 +1,8 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
+};
 
 ///Builder struct for easier [Logger](crate::Logger) creation
 ///
@@ -19,11 +23,70 @@ use std::path::{Path, PathBuf};
 pub struct Builder {
     crate_filters: Vec<(String, log::LevelFilter)>,
     mod_filters: Vec<(String, log::LevelFilter)>,
+    path_filters: Vec<(String, log::LevelFilter)>,
+    filter_env: Option<String>,
     default_level: log::LevelFilter,
     log_to_file: bool,
     log_filename: Option<PathBuf>,
+    error_log_filename: Option<PathBuf>,
     time_format: String,
-    use_color: bool,
+    use_color: Option<bool>,
+    theme: crate::Theme,
+    level_glyphs: bool,
+    show_thread: bool,
+    show_pid: bool,
+    show_hostname: bool,
+    show_location: Option<log::Level>,
+    target_width: Option<usize>,
+    pid_in_filename: bool,
+    latest_link: bool,
+    prune_logs: Option<(usize, std::time::Duration)>,
+    level_labels: Vec<(log::Level, String)>,
+    console_format: crate::Format,
+    file_format: crate::Format,
+    console_level: log::LevelFilter,
+    file_level: log::LevelFilter,
+    memory_sink: Option<usize>,
+    keep_recent: Option<usize>,
+    buffer_until_error: Option<usize>,
+    filter_file: Option<PathBuf>,
+    watch_filter_file: bool,
+    watch_config_file: Option<PathBuf>,
+    #[cfg(feature = "regex")]
+    regex_filters: Vec<(regex::Regex, log::LevelFilter)>,
+    path_prefix_strips: Vec<String>,
+    abbreviate_targets: bool,
+    multi_process_safe: bool,
+    append_to_file: bool,
+    session_banner: bool,
+    uptime_timestamps: bool,
+    #[cfg(feature = "audit")]
+    hash_chain: bool,
+    file_mode: Option<u32>,
+    write_failure_policy: crate::WriteFailurePolicy,
+    rotation: Option<crate::FileRotationPolicy>,
+    log_panics: bool,
+    console_stream: crate::ConsoleStream,
+    warnings_to_stderr: bool,
+    reopen_on_sighup: bool,
+    output_hooks: Vec<crate::SharedOutputHook>,
+    on_error: Option<crate::SharedErrorHandler>,
+    sinks: Vec<Arc<dyn crate::Sink>>,
+    chained: Vec<Arc<dyn log::Log>>,
+    context: Vec<(String, String)>,
+    env_context: Vec<(String, String)>,
+    filter_tracing: bool,
+    capture: Option<Arc<Mutex<Vec<String>>>>,
+    multiline_indent: Option<String>,
+    multiline_style: crate::MultilineStyle,
+    max_message_length: Option<usize>,
+    redactions: Vec<crate::redact::Redaction>,
+    suppress_duplicates: bool,
+    samplings: Vec<(String, crate::Sampling)>,
+    force_flush_on_error: bool,
+    sync_mode: crate::SyncMode,
+    timezone: Option<crate::Zone>,
+    logger: OnceLock<crate::Logger>,
 }
 
 impl Default for Builder {
@@ -39,14 +102,152 @@ impl Builder {
         Self {
             crate_filters: Vec::new(),
             mod_filters: Vec::new(),
+            path_filters: Vec::new(),
+            filter_env: None,
             default_level: log::LevelFilter::Info,
             log_to_file: false,
             log_filename: None,
+            error_log_filename: None,
             time_format: String::new(),
-            use_color: true,
+            use_color: None,
+            theme: crate::Theme::new(),
+            level_glyphs: false,
+            show_thread: false,
+            show_pid: false,
+            show_hostname: false,
+            show_location: None,
+            target_width: None,
+            pid_in_filename: false,
+            latest_link: true,
+            prune_logs: None,
+            level_labels: Vec::new(),
+            console_format: crate::Format::Text,
+            file_format: crate::Format::Text,
+            console_level: log::LevelFilter::Trace,
+            file_level: log::LevelFilter::Trace,
+            memory_sink: None,
+            keep_recent: None,
+            buffer_until_error: None,
+            filter_file: None,
+            watch_filter_file: false,
+            watch_config_file: None,
+            #[cfg(feature = "regex")]
+            regex_filters: Vec::new(),
+            path_prefix_strips: Vec::new(),
+            abbreviate_targets: false,
+            multi_process_safe: false,
+            append_to_file: false,
+            session_banner: false,
+            uptime_timestamps: false,
+            #[cfg(feature = "audit")]
+            hash_chain: false,
+            file_mode: None,
+            write_failure_policy: crate::WriteFailurePolicy::Retry,
+            rotation: None,
+            log_panics: false,
+            console_stream: crate::ConsoleStream::Stdout,
+            warnings_to_stderr: false,
+            reopen_on_sighup: false,
+            output_hooks: Vec::new(),
+            on_error: None,
+            sinks: Vec::new(),
+            chained: Vec::new(),
+            context: Vec::new(),
+            env_context: Vec::new(),
+            filter_tracing: false,
+            capture: None,
+            multiline_indent: None,
+            multiline_style: crate::MultilineStyle::Raw,
+            max_message_length: None,
+            redactions: Vec::new(),
+            suppress_duplicates: false,
+            samplings: Vec::new(),
+            force_flush_on_error: false,
+            sync_mode: crate::SyncMode::Never,
+            timezone: None,
+            logger: OnceLock::new(),
         }
     }
 
+    ///Creates a new builder that reads filters from the environment variable `var`
+    ///
+    ///The variable uses the same syntax as [`parse_filters`](Self::parse_filters). It is read when
+    ///the logger is created and its filters and default level take priority over the ones set in
+    ///code. An invalid value is reported to stderr and ignored.
+    ///
+    ///Example:
+    ///```
+    ///use lunar_logger::Builder;
+    ///
+    ///Builder::from_env("MY_APP_LOG")
+    ///    .add_crate_filter("wgpu", log::LevelFilter::Warn)
+    ///    .init()
+    ///    .unwrap();
+    ///```
+    #[must_use]
+    pub fn from_env(var: &str) -> Self {
+        let mut builder = Self::new();
+        builder.filter_env = Some(var.to_owned());
+        builder
+    }
+
+    ///Creates a new builder that reads filters from the `RUST_LOG` environment variable
+    ///
+    ///See [`from_env`](Self::from_env)
+    #[must_use]
+    pub fn from_default_env() -> Self {
+        Self::from_env("RUST_LOG")
+    }
+
+    ///Creates a new builder from a TOML configuration file
+    ///
+    ///The file can set the default level, filters, log file, rotation, color and time format. All
+    ///keys are optional. Filter targets follow the same rules as in
+    ///[`parse_filters`](Self::parse_filters).
+    ///
+    ///Example:
+    ///```toml
+    ///default_level = "info"
+    ///file = "logs/app.log"
+    ///color = false
+    ///
+    ///[filters]
+    ///wgpu = "warn"
+    ///"my_app::net" = "trace"
+    ///
+    ///[rotation]
+    ///max_size = 10485760
+    ///schedule = "daily"
+    ///max_files = 5
+    ///#In seconds
+    ///max_age = 604800
+    ///```
+    ///
+    ///# Errors
+    ///
+    ///Returns an error if failed to read the file or if it is not a valid configuration
+    #[cfg(feature = "config")]
+    pub fn from_config_file(path: &Path) -> Result<Self, crate::LoggerError> {
+        Ok(crate::config::load(path)?.apply(Self::new()))
+    }
+
+    ///Creates a new builder from a TOML configuration file and applies changes to the file at
+    ///runtime
+    ///
+    ///On reload all filters are replaced with the ones from the file, the default level and color
+    ///are updated if they are set, other settings are not affected. The watcher is started when the
+    ///logger is enabled. See [`from_config_file`](Self::from_config_file) for the format.
+    ///
+    ///# Errors
+    ///
+    ///Returns an error if failed to read the file or if it is not a valid configuration
+    #[cfg(all(feature = "config", feature = "watch"))]
+    pub fn watch_config_file(path: &Path) -> Result<Self, crate::LoggerError> {
+        let mut builder = Self::from_config_file(path)?;
+        builder.watch_config_file = Some(path.to_owned());
+        Ok(builder)
+    }
+
     ///Adds a filter for a crate
     #[must_use]
     pub fn add_crate_filter(mut self, crate_name: &str, level: log::LevelFilter) -> Self {
@@ -61,6 +262,81 @@ impl Builder {
         self
     }
 
+    ///Adds a filter for a module path and all of its submodules, e.g. `my_crate::net`
+    ///
+    ///Path filters are checked before crate and module filters, longest paths first
+    #[must_use]
+    pub fn add_path_filter(mut self, path: &str, level: log::LevelFilter) -> Self {
+        self.path_filters.push((path.to_owned(), level));
+        self
+    }
+
+    ///Adds filters from an `env_logger` style filter string, e.g. `wgpu=warn,my_app::net=trace,info`
+    ///
+    ///Each comma separated entry is either a bare level, which sets the default level, or
+    ///`<target>=<level>`. Targets without `::` become crate filters, the rest become path filters.
+    ///A target without a level enables all of its records.
+    ///
+    ///# Errors
+    ///
+    ///Returns an error if an entry has an invalid level
+    pub fn parse_filters(mut self, filters: &str) -> Result<Self, crate::LoggerError> {
+        for (target, level) in parse_filter_string(filters)? {
+            match target {
+                None => self.default_level = level,
+                Some(target) if target.contains("::") => self.path_filters.push((target, level)),
+                Some(target) => self.crate_filters.push((target, level)),
+            }
+        }
+        Ok(self)
+    }
+
+    ///Adds a filter for all targets matching the regex
+    ///
+    ///# Errors
+    ///
+    ///Returns an error if the pattern is not a valid regex
+    #[cfg(feature = "regex")]
+    pub fn with_target_regex(
+        mut self,
+        pattern: &str,
+        level: log::LevelFilter,
+    ) -> Result<Self, crate::LoggerError> {
+        let regex = regex::Regex::new(pattern).map_err(crate::LoggerError::InvalidRegex)?;
+        self.regex_filters.push((regex, level));
+        Ok(self)
+    }
+
+    ///Adds a prefix that is removed from the target when logging
+    ///
+    ///See [`add_path_prefix_strip`](crate::Logger::add_path_prefix_strip)
+    #[must_use]
+    pub fn add_path_prefix_strip(mut self, prefix: &str) -> Self {
+        self.path_prefix_strips.push(prefix.to_owned());
+        self
+    }
+
+    ///Abbreviates targets, e.g. `wgpu_hal::vulkan::instance` to `w::v::instance`
+    ///
+    ///See [`set_abbreviate_targets`](crate::Logger::set_abbreviate_targets)
+    #[must_use]
+    pub const fn abbreviate_targets(mut self, value: bool) -> Self {
+        self.abbreviate_targets = value;
+        self
+    }
+
+    ///Disables all logging from a crate
+    #[must_use]
+    pub fn silence_crate(self, crate_name: &str) -> Self {
+        self.add_crate_filter(crate_name, log::LevelFilter::Off)
+    }
+
+    ///Disables all logging from a module
+    #[must_use]
+    pub fn silence_module(self, module_name: &str) -> Self {
+        self.add_mod_filter(module_name, log::LevelFilter::Off)
+    }
+
     ///Sets the default logging level
     #[must_use]
     pub const fn default_filter(mut self, level: log::LevelFilter) -> Self {
@@ -68,6 +344,21 @@ impl Builder {
         self
     }
 
+    ///Sets the default logging level from the `RUST_LOG` environment variable
+    ///
+    ///Only a bare level (e.g. `debug`) is used, values containing per target filters are ignored
+    ///and so is an unset variable, keeping the current default level
+    #[must_use]
+    pub fn default_level_from_env(mut self) -> Self {
+        if let Some(level) = std::env::var("RUST_LOG")
+            .ok()
+            .and_then(|v| parse_bare_level(&v))
+        {
+            self.default_level = level;
+        }
+        self
+    }
+
     ///Enables logging to file
     #[must_use]
     pub const fn log_to_file(mut self) -> Self {
@@ -75,13 +366,357 @@ impl Builder {
         self
     }
 
-    ///Sets the filename of the log file
+    ///Sets the filename of the log file, e.g. `logs/{app}-{date}.log`
+    ///
+    ///See [`set_log_file_name`](crate::Logger::set_log_file_name) for the placeholders
     #[must_use]
     pub fn log_filname(mut self, filename: &Path) -> Self {
         self.log_filename = Some(filename.to_owned());
         self
     }
 
+    ///Sets a file that `Warn` and `Error` records are written to, e.g. `errors.log`
+    ///
+    ///See [`set_error_log_file`](crate::Logger::set_error_log_file)
+    #[must_use]
+    pub fn error_log_file(mut self, filename: &Path) -> Self {
+        self.error_log_filename = Some(filename.to_owned());
+        self
+    }
+
+    ///Sets whether writes to the log file are synchronized between processes
+    ///
+    ///See [`set_multi_process_safe`](crate::Logger::set_multi_process_safe)
+    #[must_use]
+    pub const fn multi_process_safe(mut self, value: bool) -> Self {
+        self.multi_process_safe = value;
+        self
+    }
+
+    ///Sets whether records are appended to existing log files instead of replacing their contents
+    ///
+    ///See [`set_append_to_file`](crate::Logger::set_append_to_file)
+    #[must_use]
+    pub const fn append_to_file(mut self, value: bool) -> Self {
+        self.append_to_file = value;
+        self
+    }
+
+    ///Sets whether a header is written when the log file is opened
+    ///
+    ///See [`set_session_banner`](crate::Logger::set_session_banner)
+    #[must_use]
+    pub const fn session_banner(mut self, value: bool) -> Self {
+        self.session_banner = value;
+        self
+    }
+
+    ///Sets whether every line of the log file ends with a hash of the line before it
+    ///
+    ///See [`set_hash_chain`](crate::Logger::set_hash_chain)
+    #[cfg(feature = "audit")]
+    #[must_use]
+    pub const fn hash_chain(mut self, value: bool) -> Self {
+        self.hash_chain = value;
+        self
+    }
+
+    ///Sets the permissions of the log files on Unix, e.g. `0o600`
+    ///
+    ///See [`set_file_mode`](crate::Logger::set_file_mode)
+    #[cfg(unix)]
+    #[must_use]
+    pub const fn file_mode(mut self, mode: u32) -> Self {
+        self.file_mode = Some(mode);
+        self
+    }
+
+    ///Sets what the log file does when writing to it fails
+    ///
+    ///See [`set_write_failure_policy`](crate::Logger::set_write_failure_policy)
+    #[must_use]
+    pub fn write_failure_policy(mut self, policy: crate::WriteFailurePolicy) -> Self {
+        self.write_failure_policy = policy;
+        self
+    }
+
+    ///Adds a sink that receives every logged record
+    ///
+    ///See [`add_sink`](crate::Logger::add_sink)
+    #[must_use]
+    pub fn add_sink(mut self, sink: Box<dyn crate::Sink>) -> Self {
+        self.sinks.push(Arc::from(sink));
+        self
+    }
+
+    ///Forwards every record that passes the filters to another logger
+    ///
+    ///See [`add_chained_logger`](crate::Logger::add_chained_logger)
+    #[must_use]
+    pub fn chain(mut self, logger: Box<dyn log::Log>) -> Self {
+        self.chained.push(Arc::from(logger));
+        self
+    }
+
+    ///Registers a hook that is called with the formatted output of every logged record
+    ///
+    ///See [`register_output_hook`](crate::Logger::register_output_hook)
+    #[must_use]
+    pub fn add_output_hook(mut self, f: crate::OutputHook) -> Self {
+        self.output_hooks.push(Arc::from(f));
+        self
+    }
+
+    ///Sets a handler of errors that occur while logging, e.g. failed writes to the log file
+    ///
+    ///See [`set_on_error`](crate::Logger::set_on_error)
+    #[must_use]
+    pub fn on_error(mut self, f: crate::ErrorHandler) -> Self {
+        self.on_error = Some(Arc::from(f));
+        self
+    }
+
+    ///Sets whether every filter decision is printed to stderr
+    ///
+    ///See [`enable_filter_tracing`](crate::Logger::enable_filter_tracing)
+    #[must_use]
+    pub const fn filter_tracing(mut self, value: bool) -> Self {
+        self.filter_tracing = value;
+        self
+    }
+
+    ///Captures all formatted records into a vector
+    ///
+    ///Returns the builder and a handle to the vector. The contents can be read after the logger is
+    ///disabled with [`disable`](crate::disable).
+    ///
+    ///Example:
+    ///```
+    ///use lunar_logger::Builder;
+    ///
+    ///let (builder, records) = Builder::new().use_color(false).capture_to_vec();
+    ///builder.init().unwrap();
+    ///
+    ///log::info!("It works!");
+    ///lunar_logger::disable();
+    ///log::info!("Not captured");
+    ///
+    ///let records = records.lock().unwrap();
+    ///assert_eq!(records.len(), 1);
+    ///assert!(records[0].ends_with("It works!\n"));
+    ///```
+    #[must_use]
+    pub fn capture_to_vec(mut self) -> (Self, Arc<Mutex<Vec<String>>>) {
+        let capture = Arc::new(Mutex::new(Vec::new()));
+        self.capture = Some(capture.clone());
+        (self, capture)
+    }
+
+    ///Adds a context field that is included in every record
+    ///
+    ///To add the name and version of the crate see
+    ///[`inject_cargo_metadata`](crate::inject_cargo_metadata)
+    #[must_use]
+    pub fn add_context(mut self, key: &str, value: &str) -> Self {
+        self.context.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    ///Sets the indent that is prepended to every line after the first in multi-line messages
+    #[must_use]
+    pub fn multiline_indent(mut self, indent: &str) -> Self {
+        self.multiline_indent = Some(indent.to_owned());
+        self
+    }
+
+    ///Sets how the lines after the first of multi-line messages are laid out
+    ///
+    ///See [`set_multiline_style`](crate::Logger::set_multiline_style)
+    #[must_use]
+    pub const fn multiline_style(mut self, style: crate::MultilineStyle) -> Self {
+        self.multiline_style = style;
+        self
+    }
+
+    ///Sets the maximum length of messages in characters
+    ///
+    ///See [`set_max_message_length`](crate::Logger::set_max_message_length)
+    #[must_use]
+    pub const fn max_message_length(mut self, length: usize) -> Self {
+        self.max_message_length = Some(length);
+        self
+    }
+
+    ///Collapses consecutive identical records into one, followed by
+    ///`last message repeated N times`
+    ///
+    ///See [`set_suppress_duplicates`](crate::Logger::set_suppress_duplicates)
+    #[must_use]
+    pub const fn suppress_duplicates(mut self, value: bool) -> Self {
+        self.suppress_duplicates = value;
+        self
+    }
+
+    ///Samples `Debug` and `Trace` records of a module path and all of its submodules
+    ///
+    ///See [`add_sampling`](crate::Logger::add_sampling)
+    #[must_use]
+    pub fn sample(mut self, path: &str, sampling: crate::Sampling) -> Self {
+        self.samplings.push((path.to_owned(), sampling));
+        self
+    }
+
+    ///Adds a keyword that is replaced with `[REDACTED]` in every record
+    ///
+    ///See [`add_redacted_keyword`](crate::Logger::add_redacted_keyword)
+    #[must_use]
+    pub fn redact_keyword(mut self, keyword: &str) -> Self {
+        if !keyword.is_empty() {
+            self.redactions
+                .push(crate::redact::Redaction::Keyword(keyword.to_owned()));
+        }
+        self
+    }
+
+    ///Adds a pattern whose matches are replaced with `[REDACTED]` in every record
+    ///
+    ///See [`add_redaction_pattern`](crate::Logger::add_redaction_pattern)
+    ///
+    ///# Errors
+    ///
+    ///Returns an error if the pattern is not a valid regex
+    #[cfg(feature = "regex")]
+    pub fn redact_pattern(mut self, pattern: &str) -> Result<Self, crate::LoggerError> {
+        let regex = regex::Regex::new(pattern).map_err(crate::LoggerError::InvalidRegex)?;
+        self.redactions
+            .push(crate::redact::Redaction::Pattern(regex));
+        Ok(self)
+    }
+
+    ///Sets whether the log file is synced to disk after every `Error` record
+    ///
+    ///See [`set_force_flush_on_error`](crate::Logger::set_force_flush_on_error)
+    #[must_use]
+    pub const fn force_flush_on_error(mut self, value: bool) -> Self {
+        self.force_flush_on_error = value;
+        self
+    }
+
+    ///Sets when the log file is synced to disk
+    ///
+    ///See [`set_sync_mode`](crate::Logger::set_sync_mode)
+    #[must_use]
+    pub const fn sync_mode(mut self, mode: crate::SyncMode) -> Self {
+        self.sync_mode = mode;
+        self
+    }
+
+    ///Adds the value of the environment variable `var` as the context field `key`
+    ///
+    ///The variable is read when the logger is created, if it's not set the field is omitted
+    #[must_use]
+    pub fn context_from_env(mut self, var: &str, key: &str) -> Self {
+        self.env_context.push((var.to_owned(), key.to_owned()));
+        self
+    }
+
+    ///Sets whether panics are logged
+    ///
+    ///See [`set_log_panics`](crate::Logger::set_log_panics)
+    #[must_use]
+    pub const fn log_panics(mut self, value: bool) -> Self {
+        self.log_panics = value;
+        self
+    }
+
+    ///Sets the stream the console output is written to
+    ///
+    ///See [`set_console_stream`](crate::Logger::set_console_stream)
+    #[must_use]
+    pub const fn console_stream(mut self, stream: crate::ConsoleStream) -> Self {
+        self.console_stream = stream;
+        self
+    }
+
+    ///Sets whether `Warn` and `Error` records are written to stderr
+    ///
+    ///See [`set_warnings_to_stderr`](crate::Logger::set_warnings_to_stderr)
+    #[must_use]
+    pub const fn warnings_to_stderr(mut self, value: bool) -> Self {
+        self.warnings_to_stderr = value;
+        self
+    }
+
+    ///Reopens the log file when the process receives `SIGHUP`
+    ///
+    ///See [`set_reopen_on_sighup`](crate::Logger::set_reopen_on_sighup)
+    #[cfg(unix)]
+    #[must_use]
+    pub const fn reopen_on_sighup(mut self) -> Self {
+        self.reopen_on_sighup = true;
+        self
+    }
+
+    ///Sets the policy for rotating the log file
+    #[must_use]
+    pub fn rotation_policy(mut self, policy: crate::FileRotationPolicy) -> Self {
+        self.rotation = Some(policy);
+        self
+    }
+
+    ///Rotates the log file once it would grow larger than `bytes`
+    ///
+    ///Rotated files are named `<log file>.1`, `<log file>.2`, ..., see
+    ///[`FileRotationPolicy::naming_template`](crate::FileRotationPolicy::naming_template)
+    #[must_use]
+    pub fn rotate_size(mut self, bytes: u64) -> Self {
+        self.rotation = Some(self.rotation.take().unwrap_or_default().max_size(bytes));
+        self
+    }
+
+    ///Rotates the log file on a schedule
+    ///
+    ///Rotated files are named `<log file>.<period>`, e.g. `app.log.2024-01-15`, see
+    ///[`FileRotationPolicy::naming_template`](crate::FileRotationPolicy::naming_template)
+    #[must_use]
+    pub fn rotate(mut self, rotation: crate::Rotation) -> Self {
+        self.rotation = Some(self.rotation.take().unwrap_or_default().schedule(rotation));
+        self
+    }
+
+    ///Keeps at most `n` rotated log files
+    ///
+    ///See [`FileRotationPolicy::max_rotated_files`](crate::FileRotationPolicy::max_rotated_files)
+    #[must_use]
+    pub fn max_rotated_files(mut self, n: usize) -> Self {
+        self.rotation = Some(
+            self.rotation
+                .take()
+                .unwrap_or_default()
+                .max_rotated_files(n),
+        );
+        self
+    }
+
+    ///Deletes rotated log files older than `age`
+    ///
+    ///See [`FileRotationPolicy::max_age`](crate::FileRotationPolicy::max_age)
+    #[must_use]
+    pub fn max_age(mut self, age: std::time::Duration) -> Self {
+        self.rotation = Some(self.rotation.take().unwrap_or_default().max_age(age));
+        self
+    }
+
+    ///Sets whether rotated log files are compressed with gzip
+    ///
+    ///See [`FileRotationPolicy::compress`](crate::FileRotationPolicy::compress)
+    #[cfg(feature = "gzip")]
+    #[must_use]
+    pub fn compress_rotated(mut self, value: bool) -> Self {
+        self.rotation = Some(self.rotation.take().unwrap_or_default().compress(value));
+        self
+    }
+
     ///Sets the time stamp format
     #[must_use]
     pub fn time_format(mut self, format: &str) -> Self {
@@ -89,38 +724,319 @@ impl Builder {
         self
     }
 
+    ///Sets a fixed offset from UTC that is used for time stamps instead of the local time zone
+    #[must_use]
+    pub const fn timestamp_offset(mut self, offset: chrono::FixedOffset) -> Self {
+        self.timezone = Some(crate::Zone::Fixed(offset));
+        self
+    }
+
+    ///Sets whether the time elapsed since the logger was initialized is shown instead of the time
+    ///stamp
+    ///
+    ///See [`set_uptime_timestamps`](crate::Logger::set_uptime_timestamps)
+    #[must_use]
+    pub const fn uptime_timestamps(mut self, value: bool) -> Self {
+        self.uptime_timestamps = value;
+        self
+    }
+
+    ///Sets an IANA time zone, e.g. `Europe/Berlin`, that is used for time stamps instead of the
+    ///local time zone
+    ///
+    ///See [`set_timezone`](crate::Logger::set_timezone)
+    ///
+    ///# Errors
+    ///
+    ///Returns an error if the time zone is not known
+    #[cfg(feature = "timezone")]
+    pub fn timezone(mut self, name: &str) -> Result<Self, crate::LoggerError> {
+        self.timezone = Some(crate::Zone::parse(name)?);
+        Ok(self)
+    }
+
+    ///Sets the colors used in the console output
+    #[must_use]
+    pub const fn theme(mut self, theme: crate::Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    ///Sets whether records are prefixed with a glyph of their level
+    ///
+    ///See [`set_level_glyphs`](crate::Logger::set_level_glyphs)
+    #[must_use]
+    pub const fn level_glyphs(mut self, value: bool) -> Self {
+        self.level_glyphs = value;
+        self
+    }
+
+    ///Sets whether records include the name or ID of the thread they were logged from
+    ///
+    ///See [`set_show_thread`](crate::Logger::set_show_thread)
+    #[must_use]
+    pub const fn show_thread(mut self, value: bool) -> Self {
+        self.show_thread = value;
+        self
+    }
+
+    ///Sets whether records include the ID of the process
+    ///
+    ///See [`set_show_pid`](crate::Logger::set_show_pid)
+    #[must_use]
+    pub const fn show_pid(mut self, value: bool) -> Self {
+        self.show_pid = value;
+        self
+    }
+
+    ///Sets whether records include the host name of the machine
+    ///
+    ///See [`set_show_hostname`](crate::Logger::set_show_hostname)
+    #[must_use]
+    pub const fn show_hostname(mut self, value: bool) -> Self {
+        self.show_hostname = value;
+        self
+    }
+
+    ///Includes the source file and line in records of the `level` and more verbose
+    ///
+    ///See [`set_show_location`](crate::Logger::set_show_location)
+    #[must_use]
+    pub const fn show_location(mut self, level: log::Level) -> Self {
+        self.show_location = Some(level);
+        self
+    }
+
+    ///Pads or truncates the target to `width` characters, so that messages line up
+    ///
+    ///See [`set_target_width`](crate::Logger::set_target_width)
+    #[must_use]
+    pub const fn target_width(mut self, width: usize) -> Self {
+        self.target_width = Some(width);
+        self
+    }
+
+    ///Sets whether the default log file name includes the ID of the process, e.g.
+    ///`log-2024-05-01T12:00:00-1234.log`
+    ///
+    ///Useful when multiple instances of the program log to the same directory. Has no effect if the
+    ///file name is set with [`log_filname`](Self::log_filname).
+    #[must_use]
+    pub const fn pid_in_filename(mut self, value: bool) -> Self {
+        self.pid_in_filename = value;
+        self
+    }
+
+    ///Sets whether a `latest.log` link to the log file is created next to it, enabled by default
+    ///
+    ///See [`set_latest_link`](crate::Logger::set_latest_link)
+    #[must_use]
+    pub const fn latest_link(mut self, value: bool) -> Self {
+        self.latest_link = value;
+        self
+    }
+
+    ///Deletes the log files of previous runs on startup, keeping the `keep_last` newest files that
+    ///are not older than `max_age`
+    ///
+    ///See [`set_prune_logs`](crate::Logger::set_prune_logs)
+    #[must_use]
+    pub const fn prune_logs(mut self, keep_last: usize, max_age: std::time::Duration) -> Self {
+        self.prune_logs = Some((keep_last, max_age));
+        self
+    }
+
+    ///Sets the label of the `level`, e.g. `WARNING` instead of `WARN`
+    ///
+    ///See [`set_level_label`](crate::Logger::set_level_label)
+    #[must_use]
+    pub fn level_label(mut self, level: log::Level, label: &str) -> Self {
+        self.level_labels.push((level, label.to_owned()));
+        self
+    }
+
+    ///Sets the most verbose level written to the console
+    ///
+    ///See [`set_console_level`](crate::Logger::set_console_level)
+    #[must_use]
+    pub const fn console_level(mut self, level: log::LevelFilter) -> Self {
+        self.console_level = level;
+        self
+    }
+
+    ///Sets the most verbose level written to the log file
+    ///
+    ///See [`set_file_level`](crate::Logger::set_file_level)
+    #[must_use]
+    pub const fn file_level(mut self, level: log::LevelFilter) -> Self {
+        self.file_level = level;
+        self
+    }
+
+    ///Sets the format of the console output
+    #[must_use]
+    pub fn console_format(mut self, format: crate::Format) -> Self {
+        self.console_format = format;
+        self
+    }
+
+    ///Sets the format of the log file
+    #[must_use]
+    pub fn file_format(mut self, format: crate::Format) -> Self {
+        self.file_format = format;
+        self
+    }
+
     ///Sets whether the logger will use color
     ///
-    ///Default is true
+    ///See [`use_color`](crate::Logger::use_color)
     #[must_use]
     pub const fn use_color(mut self, value: bool) -> Self {
-        self.use_color = value;
+        self.use_color = Some(value);
         self
     }
 
-    ///Crates the [Logger](crate::Logger) from the builder
+    ///Enables logging into an in-memory buffer of at most `max_bytes`
+    ///
+    ///The buffer can be retrieved with [`memory_sink`](crate::memory_sink) after the logger is
+    ///enabled
+    #[must_use]
+    pub const fn memory_sink(mut self, max_bytes: usize) -> Self {
+        self.memory_sink = Some(max_bytes);
+        self
+    }
+
+    ///Keeps the last `capacity` records in memory
     ///
-    ///# Panics
+    ///See [`keep_recent`](crate::Logger::keep_recent)
+    #[must_use]
+    pub const fn keep_recent(mut self, capacity: usize) -> Self {
+        self.keep_recent = Some(capacity);
+        self
+    }
+
+    ///Keeps the last `capacity` `Debug` and `Trace` records out of the log file until an `Error`
+    ///record is logged
+    ///
+    ///See [`set_buffer_until_error`](crate::Logger::set_buffer_until_error)
+    #[must_use]
+    pub const fn buffer_until_error(mut self, capacity: usize) -> Self {
+        self.buffer_until_error = Some(capacity);
+        self
+    }
+
+    ///Loads filters from a filter file
+    ///
+    ///If the file can't be loaded, the error is reported to the [`on_error`](Self::on_error)
+    ///handler when the logger is created. See [`load_filter_file`](crate::Logger::load_filter_file)
+    #[must_use]
+    pub fn filter_file(mut self, path: &Path) -> Self {
+        self.filter_file = Some(path.to_owned());
+        self
+    }
+
+    ///Loads filters from a filter file and reloads them whenever the file changes
+    ///
+    ///If the file can't be loaded, the error is reported to the [`on_error`](Self::on_error)
+    ///handler when the logger is created. See
+    ///[`watch_filter_file`](crate::Logger::watch_filter_file)
+    #[cfg(feature = "watch")]
+    #[must_use]
+    pub fn watch_filter_file(mut self, path: &Path) -> Self {
+        self.filter_file = Some(path.to_owned());
+        self.watch_filter_file = true;
+        self
+    }
+
+    ///Crates the [Logger](crate::Logger) from the builder
     ///
-    ///Will panic if the log filename is not a valid filename
+    ///An invalid log filename or a filter file that can't be loaded is reported to the
+    ///[`on_error`](Self::on_error) handler and ignored
     #[must_use]
     pub fn create(self) -> super::Logger {
+        self.build()
+    }
+
+    fn build(&self) -> super::Logger {
         let mut logger = crate::Logger::new();
+        //Set first, so that it receives the errors of the other settings
+        logger.on_error.clone_from(&self.on_error);
 
         //100 disable color on wasm
         #[cfg(not(target_arch = "wasm32"))]
-        {
-            logger.use_color = self.use_color;
+        if let Some(value) = self.use_color {
+            logger.use_color(value);
+        }
+        logger.set_theme(self.theme);
+        logger.set_level_glyphs(self.level_glyphs);
+        logger.set_show_thread(self.show_thread);
+        logger.set_latest_link(self.latest_link);
+        if let Some((keep_last, max_age)) = self.prune_logs {
+            logger.set_prune_logs(keep_last, max_age);
         }
+        logger.set_show_pid(self.show_pid);
+        logger.set_show_hostname(self.show_hostname);
+        logger.set_show_location(self.show_location);
+        logger.set_target_width(self.target_width);
+        for (level, label) in &self.level_labels {
+            logger.set_level_label(*level, label);
+        }
+        logger.set_console_format(self.console_format.clone());
+        logger.set_file_format(self.file_format.clone());
+        logger.set_console_level(self.console_level);
+        logger.set_file_level(self.file_level);
 
-        logger.set_default_filter(self.default_level);
+        let mut default_level = self.default_level;
+        let mut crate_filters = Vec::new();
+        let mut path_filters = Vec::new();
 
-        for (name, level) in self.crate_filters {
-            logger.add_filter(&name, crate::FilterType::Crate, level);
+        //Filters from the environment are added first, so that they take priority
+        if let Some(var) = &self.filter_env {
+            match std::env::var(var).map(|v| parse_filter_string(&v)) {
+                Ok(Ok(filters)) => {
+                    for (target, level) in filters {
+                        match target {
+                            None => default_level = level,
+                            Some(target) if target.contains("::") => {
+                                path_filters.push((target, level));
+                            }
+                            Some(target) => crate_filters.push((target, level)),
+                        }
+                    }
+                }
+                Ok(Err(e)) => crate::report_error(logger.on_error.as_ref(), e),
+                Err(_) => {}
+            }
         }
-        for (name, level) in self.mod_filters {
-            logger.add_filter(&name, crate::FilterType::Module, level);
+        crate_filters.extend(self.crate_filters.iter().cloned());
+        path_filters.extend(self.path_filters.iter().cloned());
+
+        logger.set_default_filter(default_level);
+
+        //More specific paths have to be checked first
+        path_filters.sort_by_key(|f| std::cmp::Reverse(f.0.len()));
+        for (name, level) in &path_filters {
+            logger.add_filter(name, crate::FilterType::Path, *level);
         }
+        for (name, level) in &crate_filters {
+            logger.add_filter(name, crate::FilterType::Crate, *level);
+        }
+        for (name, level) in &self.mod_filters {
+            logger.add_filter(name, crate::FilterType::Module, *level);
+        }
+
+        #[cfg(feature = "regex")]
+        logger.regex_filters.clone_from(&self.regex_filters);
+
+        for prefix in &self.path_prefix_strips {
+            logger.add_path_prefix_strip(prefix);
+        }
+        logger.set_abbreviate_targets(self.abbreviate_targets);
+
+        if let Some(zone) = self.timezone {
+            logger.timezone = zone;
+        }
+        logger.set_uptime_timestamps(self.uptime_timestamps);
 
         if !self.time_format.is_empty() {
             logger.set_timestamp_format(&self.time_format);
@@ -129,11 +1045,100 @@ impl Builder {
         if self.log_to_file {
             logger.set_log_to_file();
 
-            if let Some(f) = self.log_filename {
-                logger.set_log_file_name(&f).unwrap();
+            let result = if let Some(f) = &self.log_filename {
+                logger.set_log_file_name(f)
+            } else if self.pid_in_filename {
+                #[cfg(not(target_arch = "wasm32"))]
+                let result =
+                    crate::generate_log_name(true).map_or(Ok(()), |f| logger.set_log_file_name(&f));
+                #[cfg(target_arch = "wasm32")]
+                let result = Ok(());
+                result
+            } else {
+                Ok(())
+            };
+            if let Err(e) = result {
+                crate::report_error(logger.on_error.as_ref(), e);
+            }
+        }
+
+        if let Some(f) = &self.error_log_filename {
+            if let Err(e) = logger.set_error_log_file(f) {
+                crate::report_error(logger.on_error.as_ref(), e);
             }
         }
 
+        logger.set_multi_process_safe(self.multi_process_safe);
+        logger.set_append_to_file(self.append_to_file);
+        logger.set_session_banner(self.session_banner);
+        #[cfg(feature = "audit")]
+        logger.set_hash_chain(self.hash_chain);
+        logger.set_write_failure_policy(self.write_failure_policy.clone());
+        #[cfg(unix)]
+        logger.set_file_mode(self.file_mode);
+        logger.set_log_panics(self.log_panics);
+        logger.set_console_stream(self.console_stream);
+        logger.set_warnings_to_stderr(self.warnings_to_stderr);
+        #[cfg(unix)]
+        logger.set_reopen_on_sighup(self.reopen_on_sighup);
+        if let Some(policy) = &self.rotation {
+            logger.set_rotation_policy(policy.clone());
+        }
+        logger.set_force_flush_on_error(self.force_flush_on_error);
+        logger.set_sync_mode(self.sync_mode);
+        logger.output_hooks.clone_from(&self.output_hooks);
+        logger.context.clone_from(&self.context);
+        for (var, key) in &self.env_context {
+            if let Ok(value) = std::env::var(var) {
+                logger.add_context(key, &value);
+            }
+        }
+        logger.enable_filter_tracing(self.filter_tracing);
+        if let Some(capture) = &self.capture {
+            logger
+                .sinks
+                .push(Arc::new(crate::sink::CaptureSink(capture.clone())));
+        }
+        for sink in &self.sinks {
+            logger.add_shared_sink(sink.clone());
+        }
+        logger.chained.extend(self.chained.iter().cloned());
+        logger.multiline_indent.clone_from(&self.multiline_indent);
+        logger.set_multiline_style(self.multiline_style);
+        logger.set_max_message_length(self.max_message_length);
+        logger.redactions.clone_from(&self.redactions);
+        logger.set_suppress_duplicates(self.suppress_duplicates);
+        for (path, sampling) in &self.samplings {
+            logger.add_sampling(path, *sampling);
+        }
+        logger
+            .watched_config_file
+            .clone_from(&self.watch_config_file);
+
+        if let Some(path) = &self.filter_file {
+            #[cfg(feature = "watch")]
+            let result = if self.watch_filter_file {
+                logger.watch_filter_file(path)
+            } else {
+                logger.load_filter_file(path)
+            };
+            #[cfg(not(feature = "watch"))]
+            let result = logger.load_filter_file(path);
+
+            //A missing or invalid filter file must not prevent logging
+            if let Err(e) = result {
+                crate::report_error(logger.on_error.as_ref(), e);
+            }
+        }
+
+        if let Some(max_bytes) = self.memory_sink {
+            logger.log_to_memory(max_bytes);
+        }
+        if let Some(capacity) = self.keep_recent {
+            logger.keep_recent(capacity);
+        }
+        logger.set_buffer_until_error(self.buffer_until_error);
+
         logger
     }
 
@@ -142,11 +1147,122 @@ impl Builder {
     ///# Errors
     ///
     ///see [`enable_logger`](crate::Logger::enable_logger)
+    pub fn init(self) -> Result<crate::LoggerHandle, crate::LoggerError> {
+        self.create().enable_logger()
+    }
+
+    ///Creates the logger and sets it to be the logger of the program, unless a logger is already
+    ///in use
     ///
-    ///# Panics
+    ///# Errors
     ///
-    ///Will panic if the log filename is not a valid filename
-    pub fn init(self) -> Result<(), crate::LoggerError> {
-        self.create().enable_logger()
+    ///see [`enable_logger_or_ignore`](crate::Logger::enable_logger_or_ignore)
+    pub fn init_or_ignore(self) -> Result<crate::LoggerHandle, crate::LoggerError> {
+        self.create().enable_logger_or_ignore()
+    }
+
+    ///Returns the most verbose level enabled by the filters, to pass to [`log::set_max_level`]
+    ///when installing the builder as a logger directly
+    ///
+    ///The [Logger](crate::Logger) used by the builder is created if it wasn't yet
+    #[must_use]
+    pub fn max_level(&self) -> log::LevelFilter {
+        self.logger().max_level()
+    }
+
+    pub(crate) fn logger(&self) -> &crate::Logger {
+        self.logger.get_or_init(|| {
+            let logger = self.build();
+            if let Err(e) = logger.open_log_file() {
+                crate::report_error(logger.on_error.as_ref(), e);
+            }
+            logger.start_uptime();
+            logger
+        })
+    }
+}
+
+///Parses an `env_logger` style filter string into targets and levels
+///
+///An entry without a target sets the default level
+pub(crate) fn parse_filter_string(
+    filters: &str,
+) -> Result<Vec<(Option<String>, log::LevelFilter)>, crate::LoggerError> {
+    let mut parsed = Vec::new();
+
+    for entry in filters.split(',').map(str::trim) {
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || crate::LoggerError::InvalidFilter(entry.to_owned());
+
+        match entry.split_once('=') {
+            Some((target, level)) => {
+                let target = target.trim();
+                if target.is_empty() {
+                    return Err(invalid());
+                }
+                let level = log::LevelFilter::from_str(level.trim()).map_err(|_| invalid())?;
+                parsed.push((Some(target.to_owned()), level));
+            }
+            None => match log::LevelFilter::from_str(entry) {
+                Ok(level) => parsed.push((None, level)),
+                Err(_) => parsed.push((Some(entry.to_owned()), log::LevelFilter::Trace)),
+            },
+        }
+    }
+
+    Ok(parsed)
+}
+
+///Parses a level name without a target
+pub(crate) fn parse_bare_level(value: &str) -> Option<log::LevelFilter> {
+    if value.contains('=') {
+        return None;
+    }
+    log::LevelFilter::from_str(value.trim()).ok()
+}
+
+///Allows using the builder as a logger directly
+///
+///The [Logger](crate::Logger) is created on first use and is not set as the program logger.
+///Installing the builder with [`log::set_boxed_logger`] alone doesn't enable any records: the
+///`log` crate doesn't tell a logger that it was installed, and its max level stays
+///[`Off`](log::LevelFilter::Off), so [`log::set_max_level`] has to be called with
+///[`max_level`](Builder::max_level). Use [`init`](Builder::init) to have that done
+///automatically. Filter file watching is not supported.
+///
+///Example:
+///
+///```
+///use lunar_logger::Builder;
+///
+///let (logger, records) = Builder::new()
+///    .default_filter(log::LevelFilter::Info)
+///    .use_color(false)
+///    .capture_to_vec();
+///let max_level = logger.max_level();
+///log::set_boxed_logger(Box::new(logger)).unwrap();
+///log::set_max_level(max_level);
+///
+///log::info!("It works!");
+///log::debug!("Filtered out");
+///
+///let records = records.lock().unwrap();
+///assert_eq!(records.len(), 1);
+///assert!(records[0].ends_with("It works!\n"));
+///```
+impl log::Log for Builder {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.logger().enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.logger().log(record);
+    }
+
+    fn flush(&self) {
+        self.logger().flush();
     }
 }
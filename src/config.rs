@@ -0,0 +1,184 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{Builder, FileRotationPolicy, Filter, FilterType, Logger, LoggerError, Rotation};
+
+///Contents of a configuration file
+///
+///Example:
+///```toml
+///default_level = "info"
+///file = "logs/app.log"
+///color = false
+///time_format = "%H:%M:%S"
+///
+///[filters]
+///wgpu = "warn"
+///"my_app::net" = "trace"
+///
+///[rotation]
+///max_size = 10485760
+///schedule = "daily"
+///max_files = 5
+///max_age = 604800
+///naming_template = "app-{date}-{index}.log"
+///compress = true
+///```
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    default_level: Option<log::LevelFilter>,
+    filters: BTreeMap<String, log::LevelFilter>,
+    file: Option<PathBuf>,
+    color: Option<bool>,
+    time_format: Option<String>,
+    rotation: Option<RotationConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RotationConfig {
+    max_size: Option<u64>,
+    schedule: Option<Schedule>,
+    max_files: Option<usize>,
+    //In seconds
+    max_age: Option<u64>,
+    naming_template: Option<String>,
+    #[cfg(feature = "gzip")]
+    compress: Option<bool>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Schedule {
+    Hourly,
+    Daily,
+}
+
+impl From<Schedule> for Rotation {
+    fn from(value: Schedule) -> Self {
+        match value {
+            Schedule::Hourly => Self::Hourly,
+            Schedule::Daily => Self::Daily,
+        }
+    }
+}
+
+impl RotationConfig {
+    fn policy(&self) -> FileRotationPolicy {
+        let mut policy = FileRotationPolicy::new();
+
+        if let Some(bytes) = self.max_size {
+            policy = policy.max_size(bytes);
+        }
+        if let Some(schedule) = self.schedule {
+            policy = policy.schedule(schedule.into());
+        }
+        if let Some(n) = self.max_files {
+            policy = policy.max_rotated_files(n);
+        }
+        if let Some(secs) = self.max_age {
+            policy = policy.max_age(Duration::from_secs(secs));
+        }
+        if let Some(template) = &self.naming_template {
+            policy = policy.naming_template(template);
+        }
+        #[cfg(feature = "gzip")]
+        if let Some(compress) = self.compress {
+            policy = policy.compress(compress);
+        }
+
+        policy
+    }
+}
+
+impl Config {
+    ///Filters of the configuration, with the same rules as for filter strings
+    ///
+    ///Path filters come first, longest paths first
+    fn filters(&self) -> Vec<Filter> {
+        let mut filters: Vec<Filter> = self
+            .filters
+            .iter()
+            .map(|(target, level)| {
+                let filter_type = if target.contains("::") {
+                    FilterType::Path
+                } else {
+                    FilterType::Crate
+                };
+                (target.clone(), filter_type, *level)
+            })
+            .collect();
+
+        filters.sort_by_key(|f| (f.1 != FilterType::Path, std::cmp::Reverse(f.0.len())));
+        filters
+    }
+
+    ///Applies the configuration on top of the `builder`
+    pub(crate) fn apply(self, mut builder: Builder) -> Builder {
+        if let Some(level) = self.default_level {
+            builder = builder.default_filter(level);
+        }
+
+        for (target, filter_type, level) in self.filters() {
+            builder = match filter_type {
+                FilterType::Path => builder.add_path_filter(&target, level),
+                _ => builder.add_crate_filter(&target, level),
+            };
+        }
+
+        if let Some(path) = &self.file {
+            builder = builder.log_to_file().log_filname(path);
+        }
+        if let Some(color) = self.color {
+            builder = builder.use_color(color);
+        }
+        if let Some(format) = &self.time_format {
+            builder = builder.time_format(format);
+        }
+        if let Some(rotation) = &self.rotation {
+            builder = builder.rotation_policy(rotation.policy());
+        }
+
+        builder
+    }
+}
+
+///Parses the contents of a configuration file
+pub(crate) fn parse(contents: &str) -> Result<Config, LoggerError> {
+    toml::from_str(contents).map_err(LoggerError::InvalidConfig)
+}
+
+///Reads and parses a configuration file
+pub(crate) fn load(path: &Path) -> Result<Config, LoggerError> {
+    let contents = std::fs::read_to_string(path).map_err(LoggerError::FileError)?;
+    parse(&contents)
+}
+
+///Spawns a thread that applies the filters, default level and color of the configuration file at
+///`path` to the `logger` whenever it changes
+#[cfg(feature = "watch")]
+pub(crate) fn watch(logger: &Arc<Logger>, path: PathBuf) -> Result<(), LoggerError> {
+    crate::filter_file::on_change(logger, path, |logger, path| match load(path) {
+        Ok(config) => reload(logger, &config),
+        Err(e) => crate::report_error(logger.on_error.as_ref(), e),
+    })
+}
+
+///Applies the settings of the configuration that can change at runtime
+pub(crate) fn reload(logger: &Logger, config: &Config) {
+    if let Some(level) = config.default_level {
+        *logger.default_level.write() = level;
+    }
+    if let Some(color) = config.color {
+        logger.set_color(color);
+    }
+    //Also updates the max level
+    logger.set_filters(config.filters());
+}
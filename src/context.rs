@@ -0,0 +1,75 @@
+//!Context fields of the current thread
+//!
+//!Fields pushed onto the context are included in every record logged from the same thread, until
+//!the returned guard is dropped. Useful for correlating the records of a single request.
+//!
+//!Example:
+//!```
+//!use lunar_logger::context;
+//!
+//!let _request = context::push("request_id", 42);
+//!log::info!("Handling the request");
+//!```
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    marker::PhantomData,
+};
+
+thread_local! {
+    static FIELDS: RefCell<Vec<(usize, String, String)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_ID: Cell<usize> = const { Cell::new(0) };
+}
+
+///Removes its context field from the current thread when dropped
+#[must_use = "the context field is removed when the guard is dropped"]
+pub struct ContextGuard {
+    id: usize,
+    //The field belongs to the thread that pushed it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let _ = FIELDS.try_with(|fields| fields.borrow_mut().retain(|(id, ..)| *id != self.id));
+    }
+}
+
+///Adds a context field to the current thread, that is included in every record until the guard is
+///dropped
+pub fn push(key: &str, value: impl Display) -> ContextGuard {
+    let id = NEXT_ID.with(|next| {
+        let id = next.get();
+        next.set(id.wrapping_add(1));
+        id
+    });
+    FIELDS.with(|fields| {
+        fields
+            .borrow_mut()
+            .push((id, key.to_owned(), value.to_string()));
+    });
+
+    ContextGuard {
+        id,
+        _not_send: PhantomData,
+    }
+}
+
+///Calls `f` with the context field set, removing it afterwards
+pub fn scope<R>(key: &str, value: impl Display, f: impl FnOnce() -> R) -> R {
+    let _guard = push(key, value);
+    f()
+}
+
+///Context fields of the current thread, in the order they were pushed
+pub(crate) fn fields() -> Vec<(String, String)> {
+    FIELDS
+        .try_with(|fields| {
+            fields
+                .borrow()
+                .iter()
+                .map(|(_, k, v)| (k.clone(), v.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,210 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::{sink::SinkErrors, FormattedRecord, Sink};
+
+//Every session starts with its ephemeral public key, followed by the records
+const SESSION: u8 = 0;
+const RECORD: u8 = 1;
+
+///Writes records to a file that can only be read with the secret key of the developer, e.g. on
+///the machines of end users
+///
+///Every session generates a new key, which is agreed with the public key using X25519. Every
+///record is encrypted separately with ChaCha20-Poly1305, so the records written before a crash can
+///still be read. The file is appended to and can be read with [`decrypt_log`]. After a failed
+///write nothing more is written, as a partly written record would make the rest unreadable.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, EncryptedFileSink};
+///
+/////Generated once with `lunar_logger::generate_key_pair`, the secret key stays with the developer
+///const PUBLIC_KEY: [u8; 32] = [0; 32];
+///
+///let sink = EncryptedFileSink::new(std::path::Path::new("app.log.enc"), PUBLIC_KEY).unwrap();
+///Builder::new().add_sink(Box::new(sink)).init().unwrap();
+///```
+pub struct EncryptedFileSink {
+    state: Mutex<State>,
+    errors: SinkErrors,
+}
+
+struct State {
+    //Taken after a failed write
+    file: Option<std::fs::File>,
+    cipher: ChaCha20Poly1305,
+    //Nonce of the next record, unique as every session has its own key
+    counter: u64,
+}
+
+impl EncryptedFileSink {
+    ///Opens the file at `path` for appending and starts a session encrypted for `public_key`
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the file can't be opened or written to
+    pub fn new(path: &Path, public_key: [u8; 32]) -> std::io::Result<Self> {
+        let secret = EphemeralSecret::random();
+        let ephemeral = PublicKey::from(&secret);
+        let recipient = PublicKey::from(public_key);
+        let cipher = cipher(&secret.diffie_hellman(&recipient), &ephemeral, &recipient);
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut header = vec![SESSION];
+        header.extend_from_slice(ephemeral.as_bytes());
+        file.write_all(&header)?;
+
+        Ok(Self {
+            state: Mutex::new(State {
+                file: Some(file),
+                cipher,
+                counter: 0,
+            }),
+            errors: SinkErrors::default(),
+        })
+    }
+}
+
+///Generates a secret key and its public key for [`EncryptedFileSink`], returned in that order
+#[must_use]
+pub fn generate_key_pair() -> ([u8; 32], [u8; 32]) {
+    let secret = StaticSecret::random();
+    let public = PublicKey::from(&secret);
+    (secret.to_bytes(), public.to_bytes())
+}
+
+///Derives the key of a session from the shared secret
+fn cipher(
+    shared: &x25519_dalek::SharedSecret,
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> ChaCha20Poly1305 {
+    let mut salt = [0; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+
+    let mut key = [0; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+        .expand(b"lunar-logger encrypted log", &mut key)
+        .expect("32 bytes is a valid length");
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+fn nonce(counter: u64) -> [u8; 12] {
+    let mut nonce = [0; 12];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+///Decrypts a file written by [`EncryptedFileSink`], returning the records of all sessions
+///
+///A record that was cut off, e.g. because the program crashed while writing it, ends the file.
+///
+/// # Errors
+///
+/// returns an error if the file can't be read, or if it was not encrypted for `secret_key` or was
+/// modified
+pub fn decrypt_log(path: &Path, secret_key: [u8; 32]) -> std::io::Result<String> {
+    let invalid = |msg: &str| std::io::Error::new(ErrorKind::InvalidData, msg);
+
+    let mut data = Vec::new();
+    std::fs::File::open(path)?.read_to_end(&mut data)?;
+
+    let secret = StaticSecret::from(secret_key);
+    let recipient = PublicKey::from(&secret);
+    let mut session: Option<(ChaCha20Poly1305, u64)> = None;
+    let mut text = String::new();
+    let mut rest = &data[..];
+
+    while let Some((&tag, frame)) = rest.split_first() {
+        match tag {
+            SESSION => {
+                let Some(key) = frame.get(..32) else {
+                    break;
+                };
+                let ephemeral = PublicKey::from(<[u8; 32]>::try_from(key).unwrap());
+                let cipher = cipher(&secret.diffie_hellman(&ephemeral), &ephemeral, &recipient);
+                session = Some((cipher, 0));
+                rest = &frame[32..];
+            }
+            RECORD => {
+                let Some(len) = frame.get(..4) else {
+                    break;
+                };
+                let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+                let Some(ciphertext) = frame.get(4..4 + len) else {
+                    break;
+                };
+                let (cipher, counter) = session
+                    .as_mut()
+                    .ok_or_else(|| invalid("Record before the start of a session"))?;
+
+                let plaintext = cipher
+                    .decrypt(Nonce::from_slice(&nonce(*counter)), ciphertext)
+                    .map_err(|_| invalid("Wrong key or modified record"))?;
+                *counter += 1;
+                text.push_str(&String::from_utf8_lossy(&plaintext));
+                rest = &frame[4 + len..];
+            }
+            _ => return Err(invalid("Not an encrypted log file")),
+        }
+    }
+
+    Ok(text)
+}
+
+impl Sink for EncryptedFileSink {
+    fn write(&self, record: &FormattedRecord) {
+        let mut state = self.state.lock().unwrap();
+        if state.file.is_none() {
+            return;
+        }
+        let nonce = nonce(state.counter);
+        let Ok(ciphertext) = state
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), record.output.as_bytes())
+        else {
+            return;
+        };
+
+        //Written at once, so a crash can only cut off the last record
+        let mut frame = Vec::with_capacity(ciphertext.len() + 5);
+        frame.push(RECORD);
+        frame.extend_from_slice(&(ciphertext.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&ciphertext);
+
+        let Some(file) = &mut state.file else {
+            return;
+        };
+        match file.write_all(&frame) {
+            Ok(()) => state.counter += 1,
+            Err(e) => {
+                //The record may have been written partly, which can't be skipped when decrypting
+                state.file = None;
+                self.errors.report("encrypted log file", &e);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &mut self.state.lock().unwrap().file {
+            let _ = file.flush();
+        }
+    }
+
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+        self.errors.set(handler);
+    }
+}
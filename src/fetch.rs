@@ -0,0 +1,172 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{Headers, RequestInit};
+
+use crate::{format::json, FormattedRecord, Sink};
+
+///Sends records from the browser to a server, using `fetch`
+///
+///Records are serialized as in [`Format::Json`](crate::Format::Json) and posted as a JSON array,
+///once 50 records are collected or 5 seconds pass. Records still in the batch when the page is
+///closed are lost, unless the logger is flushed, e.g. in a `pagehide` event listener.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, FetchSink};
+///
+///let sink = FetchSink::builder("https://example.com/logs")
+///    .header("X-Api-Key", "secret")
+///    .build();
+///
+///Builder::new().add_sink(Box::new(sink)).init().unwrap();
+///```
+pub struct FetchSink {
+    shared: Arc<Shared>,
+    max_batch: usize,
+    interval: Duration,
+}
+
+///Builder of a [`FetchSink`]
+pub struct FetchSinkBuilder {
+    url: String,
+    headers: Vec<(String, String)>,
+    max_batch: usize,
+    interval: Duration,
+}
+
+//Shared with the timer callbacks
+struct Shared {
+    url: String,
+    headers: Vec<(String, String)>,
+    batch: Mutex<Vec<String>>,
+    timer_set: AtomicBool,
+}
+
+impl FetchSink {
+    ///Starts building a sink that posts to `url`
+    #[must_use]
+    pub fn builder(url: &str) -> FetchSinkBuilder {
+        FetchSinkBuilder {
+            url: url.to_owned(),
+            headers: Vec::new(),
+            max_batch: 50,
+            interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl FetchSinkBuilder {
+    ///Adds a header to the requests, e.g. an API key
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    ///Sets the number of records that are sent at once, 50 by default
+    #[must_use]
+    pub const fn max_batch(mut self, records: usize) -> Self {
+        self.max_batch = records;
+        self
+    }
+
+    ///Sets how long records wait for a batch to fill before they are sent, 5 seconds by default
+    #[must_use]
+    pub const fn flush_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    ///Creates the sink
+    #[must_use]
+    pub fn build(self) -> FetchSink {
+        FetchSink {
+            shared: Arc::new(Shared {
+                url: self.url,
+                headers: self.headers,
+                batch: Mutex::new(Vec::new()),
+                timer_set: AtomicBool::new(false),
+            }),
+            max_batch: self.max_batch,
+            interval: self.interval,
+        }
+    }
+}
+
+impl Shared {
+    ///Posts the current batch
+    fn send(&self) {
+        let records = std::mem::take(&mut *self.batch.lock().unwrap());
+        if records.is_empty() {
+            return;
+        }
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+
+        let body = JsValue::from_str(&format!("[{}]", records.join(",")));
+        let mut init = RequestInit::new();
+        init.method("POST").body(Some(&body));
+        if let Ok(headers) = Headers::new() {
+            let _ = headers.set("Content-Type", "application/json");
+            for (name, value) in &self.headers {
+                let _ = headers.set(name, value);
+            }
+            init.headers(&headers);
+        }
+
+        //Failed requests are reported by the browser, logging them would send them again
+        let _ = window.fetch_with_str_and_init(&self.url, &init);
+    }
+}
+
+impl Sink for FetchSink {
+    fn write(&self, record: &FormattedRecord) {
+        let mut json = json(record);
+        json.truncate(json.trim_end_matches('\n').len());
+
+        let full = {
+            let mut batch = self.shared.batch.lock().unwrap();
+            batch.push(json);
+            batch.len() >= self.max_batch
+        };
+        if full {
+            self.shared.send();
+            return;
+        }
+
+        //There are no threads in the browser, so the batch is sent from a timer
+        if self.shared.timer_set.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let shared = Arc::clone(&self.shared);
+        let callback = Closure::once_into_js(move || {
+            shared.timer_set.store(false, Ordering::Relaxed);
+            shared.send();
+        });
+        let timeout = i32::try_from(self.interval.as_millis()).unwrap_or(i32::MAX);
+        if window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                callback.unchecked_ref(),
+                timeout,
+            )
+            .is_err()
+        {
+            self.shared.timer_set.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) {
+        self.shared.send();
+    }
+}
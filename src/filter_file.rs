@@ -0,0 +1,125 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{Filter, FilterType, Logger, LoggerError};
+
+///Parses the contents of a filter file
+///
+///Each non empty line has the form `<crate|module|path> <name> <level>`, lines starting with `#`
+///are ignored
+///
+///Example:
+///```text
+///#Silence wgpu
+///crate wgpu warn
+///module net trace
+///path my_app::db debug
+///```
+pub(crate) fn parse(contents: &str) -> Result<Vec<Filter>, LoggerError> {
+    let mut filters = Vec::new();
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || LoggerError::InvalidFilter(line.to_owned());
+
+        let mut split = line.split_whitespace();
+        let (Some(filter_type), Some(name), Some(level), None) =
+            (split.next(), split.next(), split.next(), split.next())
+        else {
+            return Err(invalid());
+        };
+
+        let filter_type = match filter_type.to_lowercase().as_str() {
+            "crate" => FilterType::Crate,
+            "module" => FilterType::Module,
+            "path" => FilterType::Path,
+            _ => return Err(invalid()),
+        };
+        let level = log::LevelFilter::from_str(level).map_err(|_| invalid())?;
+
+        filters.push((name.to_owned(), filter_type, level));
+    }
+
+    Ok(filters)
+}
+
+///Reads and parses a filter file
+pub(crate) fn load(path: &Path) -> Result<Vec<Filter>, LoggerError> {
+    let contents = std::fs::read_to_string(path).map_err(LoggerError::FileError)?;
+    parse(&contents)
+}
+
+///Spawns a thread that reloads the filters of the `logger` whenever the file at `path` changes
+///
+///The filters set in code are kept. The thread stops when the logger is disabled or dropped.
+#[cfg(feature = "watch")]
+pub(crate) fn watch(logger: &Arc<Logger>, path: PathBuf) -> Result<(), LoggerError> {
+    on_change(logger, path, |logger, path| match load(path) {
+        Ok(filters) => {
+            let mut all = logger.code_filters.clone();
+            all.extend(filters);
+            logger.set_filters(all);
+        }
+        Err(e) => crate::report_error(logger.on_error.as_ref(), e),
+    })
+}
+
+///Spawns a thread that calls `f` whenever the file at `path` is created or modified
+///
+///The watcher is kept by the `logger`, the thread stops once it's dropped
+#[cfg(feature = "watch")]
+pub(crate) fn on_change(
+    logger: &Arc<Logger>,
+    path: PathBuf,
+    f: impl Fn(&Logger, &Path) + Send + 'static,
+) -> Result<(), LoggerError> {
+    use notify::Watcher;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(LoggerError::WatcherError)?;
+
+    //Watch the directory, so that the file being replaced by an editor is also picked up
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    watcher
+        .watch(dir, notify::RecursiveMode::NonRecursive)
+        .map_err(LoggerError::WatcherError)?;
+
+    //The thread must not keep the logger alive
+    let weak = Arc::downgrade(logger);
+    logger.watchers.lock().unwrap().push(watcher);
+
+    std::thread::spawn(move || {
+        //Ends when the watcher, and with it the sender, is dropped
+        for event in rx {
+            let Ok(event) = event else {
+                continue;
+            };
+            if !(event.kind.is_create() || event.kind.is_modify()) {
+                continue;
+            }
+            if !event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == path.file_name())
+            {
+                continue;
+            }
+
+            let Some(logger) = weak.upgrade() else {
+                break;
+            };
+            f(&logger, &path);
+        }
+    });
+
+    Ok(())
+}
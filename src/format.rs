@@ -0,0 +1,144 @@
+use std::{fmt::Write, sync::Arc};
+
+use crate::FormattedRecord;
+
+///Function that formats a record as a line of output
+///
+///The line should end with a newline
+pub type Formatter = Arc<dyn Fn(&FormattedRecord) -> String + Send + Sync>;
+
+///Format of the records written to a destination
+///
+///Example:
+///
+///```
+///use lunar_logger::{Builder, Format};
+///
+///Builder::new()
+///    .log_to_file()
+///    .file_format(Format::Json)
+///    .init()
+///    .unwrap();
+///```
+#[derive(Clone, Default)]
+pub enum Format {
+    ///`[TIMESTAMP LEVEL TARGET] MESSAGE key=value` lines, colored on the console
+    #[default]
+    Text,
+    ///One JSON object per line, with the `timestamp`, `level`, `target` and `message` fields
+    ///
+    ///Fields enabled on the logger, such as `thread`, are added next to them. Structured key-values
+    ///of the record are placed in a `fields` object.
+    Json,
+    ///Custom format
+    Custom(Formatter),
+}
+
+impl Format {
+    ///Formats the record, `None` if the text output is used as is
+    pub(crate) fn render(&self, record: &FormattedRecord) -> Option<String> {
+        match self {
+            Self::Text => None,
+            Self::Json => Some(json(record)),
+            Self::Custom(f) => Some(f(record)),
+        }
+    }
+}
+
+///Layout of the lines after the first of multi-line messages, e.g. backtraces
+///
+///Example:
+///```text
+///Raw:
+///[2024-05-01 12:00:00 ERROR app] Failed to load
+///caused by: file not found
+///
+///Align:
+///[2024-05-01 12:00:00 ERROR app] Failed to load
+///                                caused by: file not found
+///
+///Prefix:
+///[2024-05-01 12:00:00 ERROR app] Failed to load
+///[2024-05-01 12:00:00 ERROR app] caused by: file not found
+///```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MultilineStyle {
+    ///Lines are written as is
+    #[default]
+    Raw,
+    ///Lines are indented to line up with the start of the message
+    Align,
+    ///Every line is prefixed with the time stamp, level and target, so it can be parsed on its
+    ///own
+    Prefix,
+}
+
+pub(crate) fn json(record: &FormattedRecord) -> String {
+    let mut json = format!(
+        "{{\"timestamp\":\"{}\",\"level\":\"{}\",\"target\":\"{}\",\"message\":\"{}\"",
+        escape(record.timestamp),
+        record.level(),
+        escape(record.target),
+        escape(record.message),
+    );
+
+    for (key, value) in record.fields {
+        let _ = write!(json, ",\"{key}\":\"{}\"", escape(value));
+    }
+    for (key, value) in record.context {
+        let _ = write!(json, ",\"{}\":\"{}\"", escape(key), escape(value));
+    }
+
+    if !record.key_values.is_empty() {
+        let fields: Vec<String> = record
+            .key_values
+            .iter()
+            .map(|(k, v)| format!("\"{}\":\"{}\"", escape(k), escape(v)))
+            .collect();
+        let _ = write!(json, ",\"fields\":{{{}}}", fields.join(","));
+    }
+
+    json.push_str("}\n");
+    json
+}
+
+///Collects the structured key-values of the record
+pub(crate) fn key_values(record: &log::Record) -> Vec<(String, String)> {
+    struct Collect(Vec<(String, String)>);
+
+    impl<'kvs> log::kv::VisitSource<'kvs> for Collect {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            self.0.push((key.to_string(), value.to_string()));
+            Ok(())
+        }
+    }
+
+    let mut collect = Collect(Vec::new());
+    let _ = record.key_values().visit(&mut collect);
+    collect.0
+}
+
+///Escapes a string for use in a JSON string literal
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
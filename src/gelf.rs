@@ -0,0 +1,201 @@
+use std::{
+    fmt::Write,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{format::escape, resolve, sink::SinkErrors, FormattedRecord, Sink};
+
+///Magic bytes at the start of every chunk
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+///Size of the chunk header, magic bytes, message ID, sequence number and sequence count
+const CHUNK_HEADER: usize = 12;
+///Graylog drops messages with more chunks
+const MAX_CHUNKS: usize = 128;
+
+///Sends records to Graylog, as GELF messages over UDP
+///
+///Messages include the `level`, `host`, `_target`, `_file` and `_line` fields, the fields enabled
+///on the logger, the context fields and the structured key-values of the record as additional
+///fields. Characters of their names that Graylog doesn't allow are replaced with `_`, and `id`,
+///which is reserved, is renamed to `id_`. Messages larger than the chunk size are split into
+///chunks.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, GelfSink};
+///
+///let gelf = GelfSink::udp("graylog.local:12201")
+///    .unwrap()
+///    .field("environment", "production");
+///
+///Builder::new().add_sink(Box::new(gelf)).init().unwrap();
+///```
+pub struct GelfSink {
+    socket: UdpSocket,
+    host: String,
+    chunk_size: usize,
+    fields: Vec<(String, String)>,
+    next_id: AtomicU64,
+    errors: SinkErrors,
+}
+
+impl GelfSink {
+    ///Sends messages to the GELF UDP input at `addr`, usually on port 12201
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the address can't be resolved or the socket can't be created
+    pub fn udp(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let addr = resolve(addr)?[0];
+        let local: SocketAddr = if addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0; 16], 0).into()
+        };
+
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+
+        //Message IDs only need to be unique for a couple of seconds
+        let seed = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+        Ok(Self {
+            socket,
+            host: crate::hostname().unwrap_or("unknown").to_owned(),
+            chunk_size: 8192,
+            fields: Vec::new(),
+            next_id: AtomicU64::new(seed ^ u64::from(std::process::id())),
+            errors: SinkErrors::default(),
+        })
+    }
+
+    ///Sets the `host` field, the host name of the machine by default
+    #[must_use]
+    pub fn host(mut self, host: &str) -> Self {
+        host.clone_into(&mut self.host);
+        self
+    }
+
+    ///Sets the maximum size of a datagram, 8192 bytes by default
+    ///
+    ///Should be lowered to around 1420 bytes if the messages go over the internet
+    #[must_use]
+    pub const fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes;
+        self
+    }
+
+    ///Adds an additional field that is included in every message, e.g. the environment
+    #[must_use]
+    pub fn field(mut self, key: &str, value: &str) -> Self {
+        self.fields.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    ///Serializes the record as a GELF message
+    pub(crate) fn message(&self, record: &FormattedRecord) -> String {
+        let timestamp = chrono::Utc::now().timestamp_micros() as f64 / 1_000_000.0;
+        //The short message is the first line, the full message only if there are more
+        let short = record.message.lines().next().unwrap_or_default();
+
+        let mut message = format!(
+            "{{\"version\":\"1.1\",\"host\":\"{}\",\"short_message\":\"{}\",\"timestamp\":{timestamp:.6},\"level\":{},\"_target\":\"{}\"",
+            escape(&self.host),
+            escape(short),
+            crate::severity(record.level()),
+            escape(record.target),
+        );
+
+        if short.len() < record.message.len() {
+            let _ = write!(message, ",\"full_message\":\"{}\"", escape(record.message));
+        }
+        if let Some(file) = record.record.file() {
+            let _ = write!(message, ",\"_file\":\"{}\"", escape(file));
+        }
+        if let Some(line) = record.record.line() {
+            let _ = write!(message, ",\"_line\":{line}");
+        }
+
+        let fields = record.fields.iter().map(|(k, v)| (*k, v.as_str()));
+        let key_values = record
+            .context
+            .iter()
+            .chain(record.key_values)
+            .chain(&self.fields);
+        for (key, value) in fields.chain(key_values.map(|(k, v)| (k.as_str(), v.as_str()))) {
+            let _ = write!(message, ",\"_{}\":\"{}\"", field_name(key), escape(value));
+        }
+
+        message.push('}');
+        message
+    }
+
+    ///Splits the message into chunks, `None` if it needs too many
+    pub(crate) fn chunks(&self, message: &[u8]) -> Option<Vec<Vec<u8>>> {
+        if message.len() <= self.chunk_size {
+            return Some(vec![message.to_vec()]);
+        }
+
+        let data_size = self.chunk_size.saturating_sub(CHUNK_HEADER).max(1);
+        let count = message.len().div_ceil(data_size);
+        if count > MAX_CHUNKS {
+            return None;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_be_bytes();
+        let chunks = message
+            .chunks(data_size)
+            .enumerate()
+            .map(|(i, data)| {
+                let mut chunk = Vec::with_capacity(CHUNK_HEADER + data.len());
+                chunk.extend_from_slice(&CHUNK_MAGIC);
+                chunk.extend_from_slice(&id);
+                chunk.extend_from_slice(&[i as u8, count as u8]);
+                chunk.extend_from_slice(data);
+                chunk
+            })
+            .collect();
+        Some(chunks)
+    }
+}
+
+///Name of an additional field without the leading `_`, limited to the characters Graylog allows
+fn field_name(key: &str) -> String {
+    if key == "id" {
+        return "id_".to_owned();
+    }
+    key.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl Sink for GelfSink {
+    fn write(&self, record: &FormattedRecord) {
+        let message = self.message(record);
+
+        let Some(chunks) = self.chunks(message.as_bytes()) else {
+            let e = std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("message of {} bytes is too large", message.len()),
+            );
+            self.errors.report("GELF", &e);
+            return;
+        };
+        for chunk in chunks {
+            if let Err(e) = self.socket.send(&chunk) {
+                self.errors.report("GELF", &e);
+                return;
+            }
+        }
+    }
+
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+        self.errors.set(handler);
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use crate::{FilterType, Logger, LoggerError};
+
+///Handle to the program logger, returned from [`Logger::enable_logger`]
+///
+///Allows changing the configuration of the logger after it was enabled. The logger is flushed when
+///the last clone of the handle is dropped.
+///
+///Example:
+///```
+///use lunar_logger::Logger;
+///
+///let handle = Logger::new().enable_logger().unwrap();
+///
+///log::info!("In color");
+///handle.set_color(false);
+///log::info!("Without color");
+///```
+#[derive(Clone)]
+#[must_use = "the logger is flushed when the handle is dropped"]
+pub struct LoggerHandle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    logger: Arc<Logger>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        log::Log::flush(self.logger.as_ref());
+    }
+}
+
+impl LoggerHandle {
+    pub(crate) fn new(logger: Arc<Logger>) -> Self {
+        Self {
+            inner: Arc::new(Inner { logger }),
+        }
+    }
+
+    pub(crate) fn logger(&self) -> &Arc<Logger> {
+        &self.inner.logger
+    }
+
+    ///Sets the default logging level, that filters everything that does not have a dedicated filter
+    ///
+    ///The max level of the [`log`] crate is updated accordingly
+    pub fn set_default_level(&self, level: log::LevelFilter) {
+        self.logger().update_default_level(level);
+    }
+
+    ///Adds a filter for a specified module/crate, with the specified level
+    ///
+    ///If a filter for the same module/crate already exists, its level is replaced. The max level of
+    ///the [`log`] crate is updated accordingly.
+    pub fn add_filter(&self, module_name: &str, filter_type: FilterType, level: log::LevelFilter) {
+        self.logger().insert_filter(module_name, filter_type, level);
+    }
+
+    ///Removes the filter for a specified module/crate
+    ///
+    ///Returns `false` if there was no such filter
+    pub fn remove_filter(&self, module_name: &str, filter_type: FilterType) -> bool {
+        self.logger().remove_filter(module_name, filter_type)
+    }
+
+    ///Sets whether the logger will use color
+    pub fn set_color(&self, value: bool) {
+        self.logger().set_color(value);
+    }
+
+    ///Starts or stops writing into the log file
+    ///
+    ///When enabled, records are appended to the log file set with
+    ///[`Logger::set_log_file_name`]. Does nothing on the web.
+    ///
+    /// # Errors
+    ///
+    /// returns an error if failed to open the log file or if no directory was found for the
+    /// default log file
+    pub fn set_file_output(&self, value: bool) -> Result<(), LoggerError> {
+        if !value {
+            self.logger().detach_log_file();
+            return Ok(());
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.logger().attach_log_file()?;
+        Ok(())
+    }
+
+    ///Returns the most recent records, oldest first
+    ///
+    ///Empty unless enabled with [`Logger::keep_recent`]
+    #[must_use]
+    pub fn recent(&self) -> Vec<String> {
+        self.logger()
+            .recent
+            .as_ref()
+            .map(|r| r.lock().unwrap().iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    ///Returns the most recent records as text, oldest first, e.g. to attach them to a bug report
+    ///
+    ///Empty unless enabled with [`Logger::keep_recent`]
+    #[must_use]
+    pub fn recent_text(&self) -> String {
+        self.recent().concat()
+    }
+
+    ///Creates an object URL of a text file with the most recent records, that can be used as the
+    ///`href` of a download link in web builds
+    ///
+    ///Returns `None` if the URL could not be created. It should be released with
+    ///`URL.revokeObjectURL` once the file is downloaded.
+    #[cfg(target_arch = "wasm32")]
+    #[must_use]
+    pub fn recent_blob_url(&self) -> Option<String> {
+        crate::storage::blob_url(&self.recent_text())
+    }
+
+    ///Flushes the logger
+    pub fn flush(&self) {
+        log::Log::flush(self.logger().as_ref());
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use crate::sink::SinkErrors;
+
+///HTTP client of the sinks that send records to a server
+pub(crate) struct Client {
+    agent: ureq::Agent,
+    url: String,
+    headers: Vec<(String, String)>,
+    //Shared with the sink, as the client is moved to its background thread
+    pub(crate) errors: SinkErrors,
+}
+
+impl Client {
+    pub(crate) fn new(url: &str) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build(),
+            url: url.to_owned(),
+            headers: Vec::new(),
+            errors: SinkErrors::default(),
+        }
+    }
+
+    pub(crate) fn header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_owned(), value.to_owned()));
+    }
+
+    pub(crate) fn basic_auth(&mut self, user: &str, password: &str) {
+        let credentials = base64(format!("{user}:{password}").as_bytes());
+        self.header("Authorization", &format!("Basic {credentials}"));
+    }
+
+    ///Sends the body, errors are reported to the error handler of the sink
+    pub(crate) fn post(&self, content_type: &str, body: &[u8]) {
+        let mut request = self.agent.post(&self.url).set("Content-Type", content_type);
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+
+        if let Err(e) = request.send_bytes(body) {
+            self.errors
+                .report(&self.url, &std::io::Error::other(e.to_string()));
+        }
+    }
+}
+
+///Encodes the data in standard base64, with padding
+pub(crate) fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,117 @@
+use std::{os::unix::net::UnixDatagram, path::Path};
+
+use crate::{sink::SinkErrors, FormattedRecord, Sink};
+
+///Socket of the native journal protocol
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+///Sends records to the systemd journal, using the native journal protocol
+///
+///Each record is a structured entry with the `MESSAGE`, `PRIORITY`, `TARGET`, `CODE_FILE`,
+///`CODE_LINE` and `SYSLOG_IDENTIFIER` fields. The journal adds its own time stamp, so the message
+///doesn't include one.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, JournaldSink};
+///
+///Builder::new()
+///    .add_sink(Box::new(JournaldSink::new().unwrap()))
+///    .init()
+///    .unwrap();
+///```
+pub struct JournaldSink {
+    socket: UnixDatagram,
+    identifier: Option<String>,
+    errors: SinkErrors,
+}
+
+impl JournaldSink {
+    ///Connects to the journal
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the journal socket doesn't exist, e.g. if systemd is not running
+    pub fn new() -> std::io::Result<Self> {
+        Self::with_socket(Path::new(JOURNAL_SOCKET))
+    }
+
+    ///Connects to a journal socket at a different path
+    ///
+    /// # Errors
+    ///
+    /// returns an error if failed to connect to the socket
+    pub fn with_socket(path: &Path) -> std::io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+
+        let identifier = std::env::current_exe()
+            .ok()
+            .and_then(|p| Some(p.file_stem()?.to_string_lossy().into_owned()));
+        Ok(Self {
+            socket,
+            identifier,
+            errors: SinkErrors::default(),
+        })
+    }
+
+    ///Sets the `SYSLOG_IDENTIFIER` field, the name of the executable by default
+    #[must_use]
+    pub fn identifier(mut self, identifier: &str) -> Self {
+        self.identifier = Some(identifier.to_owned());
+        self
+    }
+
+    ///Serializes the record in the native journal protocol
+    pub(crate) fn entry(&self, record: &FormattedRecord) -> Vec<u8> {
+        let mut entry = Vec::new();
+
+        let message = format!(
+            "{}{}",
+            crate::format_context(record.context),
+            record.message
+        );
+        add_field(&mut entry, "MESSAGE", &message);
+        let priority = crate::severity(record.level()).to_string();
+        add_field(&mut entry, "PRIORITY", &priority);
+        add_field(&mut entry, "TARGET", record.target);
+        if let Some(file) = record.record.file() {
+            add_field(&mut entry, "CODE_FILE", file);
+        }
+        if let Some(line) = record.record.line() {
+            add_field(&mut entry, "CODE_LINE", &line.to_string());
+        }
+        if let Some(identifier) = &self.identifier {
+            add_field(&mut entry, "SYSLOG_IDENTIFIER", identifier);
+        }
+
+        entry
+    }
+}
+
+///Appends a field, values with newlines are written in the binary form, prefixed with their length
+fn add_field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    entry.extend_from_slice(name.as_bytes());
+
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+impl Sink for JournaldSink {
+    fn write(&self, record: &FormattedRecord) {
+        if let Err(e) = self.socket.send(&self.entry(record)) {
+            self.errors.report("journal", &e);
+        }
+    }
+
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+        self.errors.set(handler);
+    }
+}
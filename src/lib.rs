@@ -14,49 +14,310 @@
 //!
 //!logger.add_filter("wgpu", lunar_logger::FilterType::Crate, log::LevelFilter::Warn);
 //!logger.set_default_filter(log::LevelFilter::Info);
-//!logger.enable_logger();
+//!let _handle = logger.enable_logger().unwrap();
 //!
 //!log::info!("It works!");
 //! ```
+//!
+//! With the `tracing` feature, events of dependencies instrumented with `tracing` are forwarded to
+//! the logger as records, with the fields of the event appended to the message. They go through the
+//! same filters and outputs as other records. Events are only forwarded while no `tracing`
+//! subscriber is set.
 #![allow(unused)]
+#[cfg(feature = "audit")]
+mod audit;
+#[cfg(feature = "http")]
+mod batch;
 mod builder;
+#[cfg(feature = "config")]
+mod config;
+pub mod context;
+#[cfg(feature = "encryption")]
+mod encrypt;
+#[cfg(target_arch = "wasm32")]
+mod fetch;
+mod filter_file;
+mod format;
+#[cfg(feature = "gelf")]
+mod gelf;
+mod handle;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(all(feature = "journald", unix))]
+mod journald;
+#[cfg(feature = "http")]
+mod loki;
+#[cfg(feature = "net")]
+mod net;
+#[cfg(all(feature = "os_log", target_vendor = "apple"))]
+mod os_log;
+#[cfg(feature = "otlp")]
+mod otlp;
+mod redact;
+mod rotation;
+mod sampling;
+#[cfg(feature = "sentry")]
+mod sentry;
+mod sink;
+#[cfg(target_arch = "wasm32")]
+mod storage;
+mod sync;
+#[cfg(feature = "syslog")]
+mod syslog;
+#[cfg(any(feature = "net", feature = "syslog"))]
+mod tcp;
+pub mod test;
+mod theme;
+#[cfg(feature = "http")]
+mod webhook;
 
+#[cfg(feature = "audit")]
+pub use audit::{verify_hash_chain, ChainStatus};
 pub use builder::Builder;
+#[cfg(feature = "encryption")]
+pub use encrypt::{decrypt_log, generate_key_pair, EncryptedFileSink};
+#[cfg(target_arch = "wasm32")]
+pub use fetch::{FetchSink, FetchSinkBuilder};
+pub use format::{Format, Formatter, MultilineStyle};
+#[cfg(feature = "gelf")]
+pub use gelf::GelfSink;
+pub use handle::LoggerHandle;
+#[cfg(all(feature = "journald", unix))]
+pub use journald::JournaldSink;
+#[cfg(feature = "http")]
+pub use loki::{LokiSink, LokiSinkBuilder};
+#[cfg(feature = "net")]
+pub use net::NetworkSink;
+#[cfg(all(feature = "os_log", target_vendor = "apple"))]
+pub use os_log::OsLogSink;
+#[cfg(feature = "otlp")]
+pub use otlp::{OtlpSink, OtlpSinkBuilder};
+pub use rotation::{FileRotationPolicy, Rotation};
+pub use sampling::Sampling;
+#[cfg(feature = "sentry")]
+pub use sentry::{SentryHook, SentrySink};
+pub use sink::{ConsoleStream, FormattedRecord, Sink, SyncMode, WriteFailurePolicy};
+#[cfg(target_arch = "wasm32")]
+pub use storage::StorageSink;
+#[cfg(feature = "syslog")]
+pub use syslog::{Facility, SyslogSink};
+pub use theme::{Color, Theme};
+#[cfg(feature = "http")]
+pub use webhook::{WebhookFormat, WebhookSink, WebhookSinkBuilder};
 #[cfg(test)]
 mod tests;
 
 use std::{
+    collections::{HashMap, VecDeque},
     io::Write,
     path::{Path, PathBuf},
-    sync::{Arc, OnceLock, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
 };
 
+use redact::Redaction;
+use sampling::Sampler;
+use sink::{CaptureSink, ConsoleSink, FileOptions, FileSink, MemorySink, RecentSink};
+use sync::RwLock;
+use theme::Fg;
+
 ///Errors of the logger
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum LoggerError {
     LoggerAlreadySet,
     FileError(std::io::Error),
     InvalidFiname,
+    NoLogDirectory,
+    InvalidFilter(String),
+    WriteError(std::io::Error),
+    RotationError(std::io::Error),
+    SinkError(std::io::Error),
+    #[cfg(feature = "watch")]
+    WatcherError(notify::Error),
+    #[cfg(feature = "regex")]
+    InvalidRegex(regex::Error),
+    #[cfg(feature = "config")]
+    InvalidConfig(toml::de::Error),
+    #[cfg(feature = "timezone")]
+    InvalidTimezone(String),
+}
+
+impl std::fmt::Display for LoggerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LoggerAlreadySet => write!(f, "A logger is already set"),
+            Self::FileError(e) => write!(f, "Failed to open a log file {e}"),
+            Self::InvalidFiname => write!(f, "The log file name is a directory"),
+            Self::NoLogDirectory => write!(f, "No directory found for the default log file"),
+            Self::InvalidFilter(filter) => write!(f, "Invalid filter {filter}"),
+            Self::WriteError(e) => write!(f, "Failed to write to a log file {e}"),
+            Self::RotationError(e) => write!(f, "Failed to rotate the log file {e}"),
+            Self::SinkError(e) => write!(f, "Failed to write to a sink {e}"),
+            #[cfg(feature = "watch")]
+            Self::WatcherError(e) => write!(f, "Failed to watch a file {e}"),
+            #[cfg(feature = "regex")]
+            Self::InvalidRegex(e) => write!(f, "Invalid regex {e}"),
+            #[cfg(feature = "config")]
+            Self::InvalidConfig(e) => write!(f, "Invalid configuration {e}"),
+            #[cfg(feature = "timezone")]
+            Self::InvalidTimezone(name) => write!(f, "Unknown time zone {name}"),
+        }
+    }
+}
+
+impl std::error::Error for LoggerError {}
+
+///Time zone of the time stamps
+#[derive(Clone, Copy)]
+enum Zone {
+    Local,
+    Fixed(chrono::FixedOffset),
+    #[cfg(feature = "timezone")]
+    Named(chrono_tz::Tz),
+}
+
+impl Zone {
+    #[cfg(feature = "timezone")]
+    fn parse(name: &str) -> Result<Self, LoggerError> {
+        name.parse()
+            .map(Self::Named)
+            .map_err(|_| LoggerError::InvalidTimezone(name.to_owned()))
+    }
+}
+
+///Handler of errors that occur while logging, e.g. failed writes to the log file
+pub type ErrorHandler = Box<dyn Fn(LoggerError) + Send + Sync>;
+///Error handler shared by the logger and its sinks, see [`Sink::set_error_handler`]
+pub type SharedErrorHandler = Arc<dyn Fn(LoggerError) + Send + Sync>;
+
+///Passes the error to the handler, or writes it to stderr if there's none
+///
+///Errors are never logged, as logging them could fail again and recurse
+fn report_error(handler: Option<&SharedErrorHandler>, error: LoggerError) {
+    match handler {
+        Some(handler) => handler(error),
+        None => eprintln!("{error}"),
+    }
+}
+
+type Filter = (String, FilterType, log::LevelFilter);
+
+///Level, target and message of the last record, with the number of times it was repeated
+type LastRecord = ((log::Level, String, String), usize);
+
+///What to write to the log file when verbose records are buffered until an error
+enum ErrorBuffer {
+    ///The record was buffered, nothing is written
+    Buffered,
+    ///The buffered records followed by the error
+    Dumped(String),
+    ///The record is written as is
+    Unbuffered,
+}
+
+///The filter that decided the level of a target
+#[derive(Clone, Copy)]
+enum FilterSource {
+    Filter(usize),
+    #[cfg(feature = "regex")]
+    Regex(usize),
+    Default,
 }
 
+///Hook that receives the formatted output and the record, with the redactions applied
+pub type OutputHook = Box<dyn Fn(&str, &log::Record) + Send + Sync>;
+type SharedOutputHook = Arc<dyn Fn(&str, &log::Record) + Send + Sync>;
+
 ///Logger structure
 pub struct Logger {
-    filters: Vec<(String, FilterType, log::LevelFilter)>,
+    filters: RwLock<Vec<Filter>>,
     log_to_file: bool,
     log_filename: PathBuf,
-    default_level: log::LevelFilter,
+    latest_link: bool,
+    prune_logs: Option<(usize, std::time::Duration)>,
+    default_level: RwLock<log::LevelFilter>,
     time_format: String,
-    log_file: Option<RwLock<std::fs::File>>,
-    use_color: bool,
+    log_file: RwLock<Option<Arc<FileSink>>>,
+    use_color: AtomicBool,
+    //Color is used if the console is a terminal, until it's set explicitly
+    color_auto: AtomicBool,
+    theme: Theme,
+    level_glyphs: bool,
+    level_labels: HashMap<log::Level, String>,
+    show_thread: bool,
+    show_pid: bool,
+    show_hostname: bool,
+    //Shown for records of this level and more verbose
+    show_location: Option<log::Level>,
+    target_width: Option<usize>,
+    console_format: Format,
+    file_format: Format,
+    console_level: log::LevelFilter,
+    file_level: log::LevelFilter,
+    error_log_filename: Option<PathBuf>,
+    error_log_file: OnceLock<FileSink>,
+    memory_buffer: Option<Arc<Mutex<Vec<u8>>>>,
+    recent: Option<Arc<Mutex<VecDeque<String>>>>,
+    //Debug and Trace records waiting for an error, with the capacity
+    error_buffer: Option<(Mutex<VecDeque<String>>, usize)>,
+    watched_filter_file: Option<PathBuf>,
+    watched_config_file: Option<PathBuf>,
+    //Filters that are kept when the watched filter file is reloaded
+    #[cfg(feature = "watch")]
+    code_filters: Vec<Filter>,
+    //Dropping a watcher stops its thread
+    #[cfg(feature = "watch")]
+    watchers: Mutex<Vec<notify::RecommendedWatcher>>,
+    #[cfg(feature = "regex")]
+    regex_filters: Vec<(regex::Regex, log::LevelFilter)>,
+    path_prefix_strips: Vec<String>,
+    abbreviate_targets: bool,
+    multi_process_safe: bool,
+    append_to_file: bool,
+    session_banner: bool,
+    #[cfg(feature = "audit")]
+    hash_chain: bool,
+    file_mode: Option<u32>,
+    write_failure_policy: WriteFailurePolicy,
+    output_hooks: Vec<SharedOutputHook>,
+    on_error: Option<SharedErrorHandler>,
+    context: Vec<(String, String)>,
+    filter_tracing: bool,
+    multiline_indent: Option<String>,
+    multiline_style: MultilineStyle,
+    max_message_length: Option<usize>,
+    redactions: Vec<Redaction>,
+    suppress_duplicates: bool,
+    samplers: Vec<Sampler>,
+    last_record: Mutex<Option<LastRecord>>,
+    force_flush_on_error: bool,
+    sync_mode: SyncMode,
+    timezone: Zone,
+    uptime_timestamps: bool,
+    //Start of the elapsed time shown instead of the time stamp, set when the logger is enabled
+    uptime_start: OnceLock<f64>,
+    console: ConsoleSink,
+    sinks: Vec<Arc<dyn Sink>>,
+    chained: Vec<Arc<dyn log::Log>>,
+    rotation: Option<FileRotationPolicy>,
+    log_panics: bool,
+    reopen_on_sighup: bool,
+    reopen_requested: Arc<AtomicBool>,
 }
 
 ///Types of filter that can be added
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum FilterType {
     ///Filters by the name of the module
     Module,
     ///Filters by the crate name
     Crate,
+    ///Filters by a module path and all of its submodules, e.g. `my_crate::net`
+    Path,
 }
 
 impl Default for Logger {
@@ -71,13 +332,73 @@ impl Logger {
     #[must_use]
     pub fn new() -> Self {
         Self {
-            filters: Vec::new(),
+            filters: RwLock::new(Vec::new()),
             log_to_file: false,
-            log_filename: generate_log_name(),
-            default_level: log::LevelFilter::Info,
+            log_filename: generate_log_name(false).unwrap_or_default(),
+            latest_link: true,
+            prune_logs: None,
+            default_level: RwLock::new(log::LevelFilter::Info),
             time_format: "%Y-%m-%d %H:%M:%S".into(),
-            log_file: None,
-            use_color: true,
+            log_file: RwLock::new(None),
+            use_color: AtomicBool::new(true),
+            color_auto: AtomicBool::new(true),
+            theme: Theme::new(),
+            level_glyphs: false,
+            level_labels: HashMap::new(),
+            show_thread: false,
+            show_pid: false,
+            show_hostname: false,
+            show_location: None,
+            target_width: None,
+            console_format: Format::Text,
+            file_format: Format::Text,
+            console_level: log::LevelFilter::Trace,
+            file_level: log::LevelFilter::Trace,
+            error_log_filename: None,
+            error_log_file: OnceLock::new(),
+            memory_buffer: None,
+            recent: None,
+            error_buffer: None,
+            watched_filter_file: None,
+            watched_config_file: None,
+            #[cfg(feature = "watch")]
+            code_filters: Vec::new(),
+            #[cfg(feature = "watch")]
+            watchers: Mutex::new(Vec::new()),
+            #[cfg(feature = "regex")]
+            regex_filters: Vec::new(),
+            path_prefix_strips: Vec::new(),
+            abbreviate_targets: false,
+            multi_process_safe: false,
+            append_to_file: false,
+            session_banner: false,
+            #[cfg(feature = "audit")]
+            hash_chain: false,
+            file_mode: None,
+            write_failure_policy: WriteFailurePolicy::Retry,
+            output_hooks: Vec::new(),
+            on_error: None,
+            context: Vec::new(),
+            filter_tracing: false,
+            multiline_indent: None,
+            multiline_style: MultilineStyle::Raw,
+            max_message_length: None,
+            redactions: Vec::new(),
+            suppress_duplicates: false,
+            samplers: Vec::new(),
+            last_record: Mutex::new(None),
+            force_flush_on_error: false,
+            sync_mode: SyncMode::Never,
+            timezone: Zone::Local,
+            uptime_timestamps: false,
+            uptime_start: OnceLock::new(),
+            console: ConsoleSink::default(),
+            sinks: Vec::new(),
+            chained: Vec::new(),
+            rotation: None,
+            log_panics: false,
+            reopen_on_sighup: false,
+            reopen_requested: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -85,61 +406,468 @@ impl Logger {
     #[must_use]
     pub fn new() -> Self {
         Self {
-            filters: Vec::new(),
+            filters: RwLock::new(Vec::new()),
             log_to_file: false,
             log_filename: PathBuf::new(),
-            default_level: log::LevelFilter::Info,
+            latest_link: true,
+            prune_logs: None,
+            default_level: RwLock::new(log::LevelFilter::Info),
             time_format: "%Y-%m-%d %H:%M:%S".into(),
-            log_file: None,
-            use_color: false,
+            log_file: RwLock::new(None),
+            use_color: AtomicBool::new(false),
+            color_auto: AtomicBool::new(false),
+            theme: Theme::new(),
+            level_glyphs: false,
+            level_labels: HashMap::new(),
+            show_thread: false,
+            show_pid: false,
+            show_hostname: false,
+            show_location: None,
+            target_width: None,
+            console_format: Format::Text,
+            file_format: Format::Text,
+            console_level: log::LevelFilter::Trace,
+            file_level: log::LevelFilter::Trace,
+            error_log_filename: None,
+            error_log_file: OnceLock::new(),
+            memory_buffer: None,
+            recent: None,
+            error_buffer: None,
+            watched_filter_file: None,
+            watched_config_file: None,
+            #[cfg(feature = "watch")]
+            code_filters: Vec::new(),
+            #[cfg(feature = "watch")]
+            watchers: Mutex::new(Vec::new()),
+            #[cfg(feature = "regex")]
+            regex_filters: Vec::new(),
+            path_prefix_strips: Vec::new(),
+            abbreviate_targets: false,
+            multi_process_safe: false,
+            append_to_file: false,
+            session_banner: false,
+            #[cfg(feature = "audit")]
+            hash_chain: false,
+            file_mode: None,
+            write_failure_policy: WriteFailurePolicy::Retry,
+            output_hooks: Vec::new(),
+            on_error: None,
+            context: Vec::new(),
+            filter_tracing: false,
+            multiline_indent: None,
+            multiline_style: MultilineStyle::Raw,
+            max_message_length: None,
+            redactions: Vec::new(),
+            suppress_duplicates: false,
+            samplers: Vec::new(),
+            last_record: Mutex::new(None),
+            force_flush_on_error: false,
+            sync_mode: SyncMode::Never,
+            timezone: Zone::Local,
+            uptime_timestamps: false,
+            uptime_start: OnceLock::new(),
+            console: ConsoleSink::default(),
+            sinks: Vec::new(),
+            chained: Vec::new(),
+            rotation: None,
+            log_panics: false,
+            reopen_on_sighup: false,
+            reopen_requested: Arc::new(AtomicBool::new(false)),
         }
     }
 
     ///Consumes the logger and sets it as the program logger
     ///
+    ///Returns a handle that allows changing the configuration of the logger and flushes it when
+    ///dropped, it should be kept alive until the end of the program so that no records are lost
+    ///
+    /// # Errors
+    ///
+    /// returns an error if a logger is already in use, if failed to create a log file or if no
+    /// directory was found for the default log file
+    pub fn enable_logger(self) -> Result<LoggerHandle, LoggerError> {
+        //Checked before the log file is truncated and old logs are pruned
+        if global_logger().is_some() {
+            return Err(LoggerError::LoggerAlreadySet);
+        }
+
+        self.open_log_file()?;
+        self.start_uptime();
+
+        let max_level = self.max_level();
+
+        let logger = {
+            let mut current = INTERNAL_LOGGER.write();
+            if current.is_some() {
+                return Err(LoggerError::LoggerAlreadySet);
+            }
+
+            install_proxy()?;
+
+            let logger = Arc::new(self);
+            *current = Some(logger.clone());
+            logger
+        };
+        log::set_max_level(max_level);
+
+        #[cfg(feature = "watch")]
+        if let Some(path) = &logger.watched_filter_file {
+            filter_file::watch(&logger, path.clone())?;
+        }
+        #[cfg(all(feature = "config", feature = "watch"))]
+        if let Some(path) = &logger.watched_config_file {
+            config::watch(&logger, path.clone())?;
+        }
+
+        if logger.log_panics {
+            install_panic_hook();
+        }
+
+        #[cfg(unix)]
+        if logger.reopen_on_sighup {
+            signal_hook::flag::register(
+                signal_hook::consts::SIGHUP,
+                logger.reopen_requested.clone(),
+            )
+            .map_err(LoggerError::FileError)?;
+        }
+
+        Ok(LoggerHandle::new(logger))
+    }
+
+    ///Consumes the logger and sets it as the program logger, unless a logger is already in use
+    ///
+    ///If a logger is already in use, `self` is discarded and a handle to the existing logger is
+    ///returned
+    ///
+    ///Example:
+    ///```
+    ///use lunar_logger::Logger;
+    ///
+    ///Logger::new().enable_logger_or_ignore().unwrap();
+    ///Logger::new().enable_logger_or_ignore().unwrap();
+    ///```
+    ///
     /// # Errors
     ///
-    /// returns an error if a logger is already in use or if failed to create a log file
-    pub fn enable_logger(mut self) -> Result<(), LoggerError> {
+    /// returns an error if failed to create a log file
+    pub fn enable_logger_or_ignore(self) -> Result<LoggerHandle, LoggerError> {
+        if let Some(logger) = global_logger() {
+            return Ok(LoggerHandle::new(logger));
+        }
+
+        match self.enable_logger() {
+            Err(LoggerError::LoggerAlreadySet) => match global_logger() {
+                Some(logger) => Ok(LoggerHandle::new(logger)),
+                None => Err(LoggerError::LoggerAlreadySet),
+            },
+            r => r,
+        }
+    }
+
+    ///Opens the log file if logging to a file is enabled, truncating it unless it's appended to or
+    ///shared between processes
+    fn open_log_file(&self) -> Result<(), LoggerError> {
         //Disable file logging on the web
         #[cfg(not(target_arch = "wasm32"))]
         if self.log_to_file {
-            if let Err(e) = create_file(&self.log_filename) {
-                return Err(LoggerError::FileError(e));
+            if self.log_filename.as_os_str().is_empty() {
+                return Err(LoggerError::NoLogDirectory);
             }
+            let path = expand_file_name(&self.log_filename);
+            create_file(&path, self.file_mode, self.truncate_files())
+                .map_err(LoggerError::FileError)?;
+            self.attach_log_file_at(&path)?;
 
-            match std::fs::OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(false)
-                .open(&self.log_filename)
-            {
-                Ok(f) => self.log_file = Some(RwLock::new(f)),
-                Err(e) => return Err(LoggerError::FileError(e)),
+            if let Some((keep_last, max_age)) = self.prune_logs {
+                let result = rotation::prune_logs(&self.log_filename, &path, keep_last, max_age);
+                if let Err(e) = result {
+                    report_error(self.on_error.as_ref(), LoggerError::FileError(e));
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &self.error_log_filename {
+            if self.error_log_file.get().is_none() {
+                create_file(path, self.file_mode, self.truncate_files())
+                    .map_err(LoggerError::FileError)?;
+                //The fallback path is only used by the log file
+                let mut options = self.file_options();
+                if let WriteFailurePolicy::Fallback(_) = options.failure_policy {
+                    options.failure_policy = WriteFailurePolicy::Retry;
+                }
+                let f = FileSink::open(path, options, None).map_err(LoggerError::FileError)?;
+                let _ = self.error_log_file.set(f);
             }
         }
 
-        //Figure out the max level
+        Ok(())
+    }
+
+    ///Starts writing into the log file, appending to it if it already exists
+    fn attach_log_file(&self) -> Result<(), LoggerError> {
+        self.attach_log_file_at(&expand_file_name(&self.log_filename))
+    }
+
+    ///Starts writing into the log file at the expanded `path`
+    fn attach_log_file_at(&self, path: &Path) -> Result<(), LoggerError> {
+        let mut log_file = self.log_file.write();
+        if log_file.is_some() {
+            return Ok(());
+        }
+        //The default file name is empty if no directory was found for it
+        if path.as_os_str().is_empty() {
+            return Err(LoggerError::NoLogDirectory);
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(LoggerError::FileError)?;
+        }
+
+        let f = FileSink::open(path, self.file_options(), self.rotation.clone())
+            .map_err(LoggerError::FileError)?;
+        if self.session_banner {
+            f.write_text(&self.session_banner())
+                .map_err(LoggerError::FileError)?;
+        }
+        *log_file = Some(Arc::new(f));
+
+        if self.latest_link && path != self.log_filename {
+            let _ = link_latest(path);
+        }
+
+        Ok(())
+    }
+
+    ///Whether the log files are truncated when they are opened
+    const fn truncate_files(&self) -> bool {
+        //Other processes may already be logging into the file
+        !self.append_to_file && !self.multi_process_safe
+    }
+
+    fn file_options(&self) -> FileOptions {
+        FileOptions {
+            multi_process_safe: self.multi_process_safe,
+            force_flush_on_error: self.force_flush_on_error,
+            sync_mode: self.sync_mode,
+            #[cfg(feature = "audit")]
+            hash_chain: self.hash_chain,
+            mode: self.file_mode,
+            failure_policy: self.write_failure_policy.clone(),
+            on_error: self.on_error.clone(),
+        }
+    }
+
+    ///Stops writing into the log file, flushing it
+    fn detach_log_file(&self) {
+        //Released before flushing, as errors are logged
+        let log_file = self.log_file.write().take();
+        if let Some(f) = log_file {
+            f.flush();
+        }
+    }
+
+    ///Figures out the max level
+    fn max_level(&self) -> log::LevelFilter {
+        let default_level = *self.default_level.read();
         let max_level = self
             .filters
+            .read()
             .iter()
             .map(|i| i.2)
             .max()
-            .unwrap_or(self.default_level)
-            .max(self.default_level);
+            .unwrap_or(default_level)
+            .max(default_level);
 
-        log::set_max_level(max_level);
+        #[cfg(feature = "regex")]
+        let max_level = self
+            .regex_filters
+            .iter()
+            .map(|i| i.1)
+            .fold(max_level, Ord::max);
 
-        if INTERNAL_LOGGER.set(Arc::new(self)).is_err() {
-            return Err(LoggerError::LoggerAlreadySet);
+        max_level
+    }
+
+    ///Finds the level of the first filter matching the target
+    ///
+    ///Exact filters are checked first, then regex filters, falling back to the default level
+    fn target_level(&self, target: &str) -> (log::LevelFilter, FilterSource) {
+        for (i, (name, filter_type, level)) in self.filters.read().iter().enumerate() {
+            if filter(name, *filter_type, target) {
+                return (*level, FilterSource::Filter(i));
+            }
         }
-        let Some(logger) = INTERNAL_LOGGER.get() else {
-            return Err(LoggerError::LoggerAlreadySet);
+
+        #[cfg(feature = "regex")]
+        for (i, (regex, level)) in self.regex_filters.iter().enumerate() {
+            if regex.is_match(target) {
+                return (*level, FilterSource::Regex(i));
+            }
+        }
+
+        (*self.default_level.read(), FilterSource::Default)
+    }
+
+    ///Describes why a record was or wasn't logged
+    fn filter_trace(
+        &self,
+        target: &str,
+        msg_level: log::LevelFilter,
+        level: log::LevelFilter,
+        source: FilterSource,
+    ) -> String {
+        let matched = match source {
+            FilterSource::Filter(i) => match self.filters.read().get(i) {
+                Some((name, FilterType::Crate, _)) => format!("crate-filter[{name}]"),
+                Some((name, FilterType::Module, _)) => format!("module-filter[{name}]"),
+                Some((name, FilterType::Path, _)) => format!("path-filter[{name}]"),
+                None => "removed-filter".to_owned(),
+            },
+            #[cfg(feature = "regex")]
+            FilterSource::Regex(i) => format!("regex-filter[{}]", self.regex_filters[i].0),
+            FilterSource::Default => "default".to_owned(),
         };
-        if log::set_logger(logger.as_ref() as &dyn log::Log).is_err() {
-            Err(LoggerError::LoggerAlreadySet)
+        let decision = if msg_level > level {
+            "suppressed"
         } else {
-            Ok(())
+            "logged"
+        };
+
+        format!(
+            "FILTER: target={target} level={msg_level:?} matched {matched}={level:?} → {decision}"
+        )
+    }
+
+    ///Formats all active filters as a table
+    fn filter_table(&self) -> String {
+        let mut rows = vec![["Name".to_owned(), "Type".to_owned(), "Level".to_owned()]];
+
+        for (name, filter_type, level) in self.filters.read().iter() {
+            rows.push([
+                name.clone(),
+                format!("{filter_type:?}"),
+                format!("{level:?}"),
+            ]);
+        }
+        #[cfg(feature = "regex")]
+        for (regex, level) in &self.regex_filters {
+            rows.push([regex.to_string(), "Regex".to_owned(), format!("{level:?}")]);
+        }
+        rows.push([
+            "*".to_owned(),
+            "Default".to_owned(),
+            format!("{:?}", self.default_level.read()),
+        ]);
+
+        let widths: Vec<usize> = (0..3)
+            .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap())
+            .collect();
+
+        let border = |left: &str, middle: &str, right: &str| {
+            let lines: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            format!("{left}{}{right}\n", lines.join(middle))
+        };
+
+        let mut table = border("┌", "┬", "┐");
+        for (i, row) in rows.iter().enumerate() {
+            table.push('│');
+            for (cell, width) in row.iter().zip(&widths) {
+                table.push_str(&format!(" {cell:width$} │"));
+            }
+            table.push('\n');
+
+            if i == 0 {
+                table.push_str(&border("├", "┼", "┤"));
+            }
+        }
+        table.push_str(&border("└", "┴", "┘"));
+
+        table
+    }
+
+    ///Formats the header written at the start of every session
+    fn session_banner(&self) -> String {
+        let context = |key: &str| {
+            self.context
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        let app = context("app").map_or_else(
+            || {
+                std::env::current_exe()
+                    .ok()
+                    .and_then(|p| Some(p.file_stem()?.to_string_lossy().into_owned()))
+                    .unwrap_or_else(|| "unknown".into())
+            },
+            str::to_owned,
+        );
+        let app = match context("version") {
+            Some(version) => format!("{app} {version}"),
+            None => app,
+        };
+
+        let title = " Session started ";
+        let border = "=".repeat(32);
+        format!(
+            "{border}{title}{border}\nApp: {app}\nOS: {} {}\nStarted: {}\nPID: {}\nFilters:\n{}{}\n",
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            get_time("%Y-%m-%d %H:%M:%S%:z"),
+            std::process::id(),
+            self.filter_table(),
+            "=".repeat(border.len() * 2 + title.len()),
+        )
+    }
+
+    ///Replaces all filters and updates the max level
+    fn set_filters(&self, filters: Vec<Filter>) {
+        *self.filters.write() = filters;
+        self.update_max_level();
+    }
+
+    ///Adds a filter, replacing the existing filter for the same name and type, and updates the max
+    ///level
+    ///
+    ///The filters are kept in the order of the [Builder], path filters first, longest paths first,
+    ///then crate and module filters, as the first matching filter is used
+    fn insert_filter(&self, name: &str, filter_type: FilterType, level: log::LevelFilter) {
+        {
+            let mut filters = self.filters.write();
+            match filters
+                .iter_mut()
+                .find(|f| f.0 == name && f.1 == filter_type)
+            {
+                Some(f) => f.2 = level,
+                None => filters.push((name.to_owned(), filter_type, level)),
+            }
+            filters.sort_by_key(|(name, filter_type, _)| match filter_type {
+                FilterType::Path => (0, std::cmp::Reverse(name.len())),
+                FilterType::Crate => (1, std::cmp::Reverse(0)),
+                FilterType::Module => (2, std::cmp::Reverse(0)),
+            });
+        }
+        self.update_max_level();
+    }
+
+    ///Removes the filter for the name and type and updates the max level
+    fn remove_filter(&self, name: &str, filter_type: FilterType) -> bool {
+        let removed = {
+            let mut filters = self.filters.write();
+            let len = filters.len();
+            filters.retain(|f| f.0 != name || f.1 != filter_type);
+            filters.len() != len
+        };
+        self.update_max_level();
+        removed
+    }
+
+    ///Updates the max level if this is the program logger
+    fn update_max_level(&self) {
+        if global_logger().is_some_and(|l| std::ptr::eq(l.as_ref(), self)) {
+            log::set_max_level(self.max_level());
         }
     }
 
@@ -151,12 +879,94 @@ impl Logger {
         level: log::LevelFilter,
     ) {
         self.filters
+            .get_mut()
             .push((module_name.to_owned(), filter_type, level));
     }
 
+    ///Disables all logging from a crate
+    pub fn silence_crate(&mut self, crate_name: &str) {
+        self.add_filter(crate_name, FilterType::Crate, log::LevelFilter::Off);
+    }
+
+    ///Disables all logging from a module
+    pub fn silence_module(&mut self, module_name: &str) {
+        self.add_filter(module_name, FilterType::Module, log::LevelFilter::Off);
+    }
+
+    ///Adds a filter for all targets matching the regex
+    ///
+    ///Regex filters are checked after all crate and module filters
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the pattern is not a valid regex
+    #[cfg(feature = "regex")]
+    pub fn add_regex_filter(
+        &mut self,
+        pattern: &str,
+        level: log::LevelFilter,
+    ) -> Result<(), LoggerError> {
+        let regex = regex::Regex::new(pattern).map_err(LoggerError::InvalidRegex)?;
+        self.regex_filters.push((regex, level));
+        Ok(())
+    }
+
+    ///Adds the filters from a filter file
+    ///
+    ///Each non empty line of the file has the form `<crate|module> <name> <level>`, lines starting
+    ///with `#` are ignored
+    ///
+    /// # Errors
+    ///
+    /// returns an error if failed to read the file or if it contains an invalid filter
+    pub fn load_filter_file(&mut self, path: &Path) -> Result<(), LoggerError> {
+        let filters = filter_file::load(path)?;
+        self.filters.get_mut().extend(filters);
+        Ok(())
+    }
+
+    ///Watches the filter file and reloads the filters whenever it changes
+    ///
+    ///The filters of the file are added to the ones that are already set, as with
+    ///[`load_filter_file`](Self::load_filter_file). On reload the filters of the file are replaced
+    ///with its new contents, other settings are not affected. The watcher is started when the
+    ///logger is enabled and stopped when it's disabled.
+    ///
+    /// # Errors
+    ///
+    /// returns an error if failed to read the file or if it contains an invalid filter
+    #[cfg(feature = "watch")]
+    pub fn watch_filter_file(&mut self, path: &Path) -> Result<(), LoggerError> {
+        let filters = filter_file::load(path)?;
+        self.code_filters.clone_from(self.filters.get_mut());
+        self.filters.get_mut().extend(filters);
+        self.watched_filter_file = Some(path.to_owned());
+        Ok(())
+    }
+
     ///Sets the filename of the log file.
     ///
-    ///Logging to a file must be enabled separately
+    ///Logging to a file must be enabled separately. The filename can contain placeholders, that are
+    ///expanded when the file is opened:
+    ///
+    ///- `{app}` - name of the executable
+    ///- `{date}` - current date, e.g. `2024-05-01`
+    ///- `{time}` - current time, e.g. `12-00-00`
+    ///- `{datetime}` - ISO-8601 date and time, e.g. `2024-05-01T12:00:00`, with dashes on Windows
+    ///- `{pid}` - ID of the process
+    ///- `{hostname}` - host name of the machine
+    ///
+    ///The default filename is `log-{datetime}.log`.
+    ///
+    ///Example:
+    ///```
+    ///use lunar_logger::Logger;
+    ///
+    ///let mut logger = Logger::new();
+    ///logger
+    ///    .set_log_file_name("logs/{app}-{date}-{pid}.log".as_ref())
+    ///    .unwrap();
+    ///```
     ///
     /// # Errors
     ///
@@ -169,88 +979,1183 @@ impl Logger {
         Ok(())
     }
 
+    ///Sets whether a `latest.log` link to the log file is created next to it, enabled by default
+    ///
+    ///The link is only created if the filename has placeholders, so that `tail -f latest.log`
+    ///follows the newest file. It's a symbolic link, or a hard link on Windows if creating symbolic
+    ///links is not allowed. If neither can be created, the log file is used without a link.
+    pub fn set_latest_link(&mut self, value: bool) {
+        self.latest_link = value;
+    }
+
+    ///Deletes the log files of previous runs when the log file is opened, keeping the `keep_last`
+    ///newest runs, including the current one, that are not older than `max_age`
+    ///
+    ///Only applies if the filename has placeholders, such as the default one, so that every run
+    ///creates a new file. Only files in the directory of the log file that could have been created
+    ///from the filename are deleted, e.g. `log-2024-05-01T12:00:00.log` for `log-{datetime}.log`,
+    ///along with their rotated and compressed files, such as `.log.1.gz`.
+    pub fn set_prune_logs(&mut self, keep_last: usize, max_age: std::time::Duration) {
+        self.prune_logs = Some((keep_last, max_age));
+    }
+
+    ///Sets a file that `Warn` and `Error` records are written to, in addition to the log file
+    ///
+    ///The file is truncated when the logger is enabled, unless the log files are appended to, and
+    ///is not rotated
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the provided filename is a directory
+    pub fn set_error_log_file(&mut self, filename: &Path) -> Result<(), LoggerError> {
+        if filename.is_dir() {
+            return Err(LoggerError::InvalidFiname);
+        }
+        self.error_log_filename = Some(filename.to_owned());
+        Ok(())
+    }
+
     ///Enables logging to a file
     pub fn set_log_to_file(&mut self) {
         self.log_to_file = true;
     }
 
+    ///Sets whether panics are logged
+    ///
+    ///When enabled, a panic hook is installed once the logger is enabled. It logs the panic at the
+    ///`Error` level with the target `panic` and flushes the logger, before calling the previous
+    ///hook.
+    pub fn set_log_panics(&mut self, value: bool) {
+        self.log_panics = value;
+    }
+
+    ///Sets whether the log file is reopened when the process receives `SIGHUP`
+    ///
+    ///Allows external tools like `logrotate` to move the file, the file is reopened by the first
+    ///record logged after the signal. The signal handler is installed once the logger is enabled.
+    #[cfg(unix)]
+    pub fn set_reopen_on_sighup(&mut self, value: bool) {
+        self.reopen_on_sighup = value;
+    }
+
+    ///Sets the stream the console output is written to, stdout by default
+    ///
+    ///Has no effect on the web
+    pub fn set_console_stream(&mut self, stream: ConsoleStream) {
+        self.console.stream = stream;
+    }
+
+    ///Sets whether `Warn` and `Error` records are written to stderr
+    ///
+    ///Other records are still written to the console stream, so that the output of the program can
+    ///be piped without the errors. Has no effect on the web.
+    pub fn set_warnings_to_stderr(&mut self, value: bool) {
+        self.console.warnings_to_stderr = value;
+    }
+
+    ///Sets the policy for rotating the log file
+    pub fn set_rotation_policy(&mut self, policy: FileRotationPolicy) {
+        self.rotation = Some(policy);
+    }
+
     ///Sets the time stamp format when logging
     pub fn set_timestamp_format(&mut self, format: &str) {
         format.clone_into(&mut self.time_format);
     }
 
+    ///Sets a fixed offset from UTC that is used for time stamps instead of the local time zone
+    pub fn set_timestamp_offset(&mut self, offset: chrono::FixedOffset) {
+        self.timezone = Zone::Fixed(offset);
+    }
+
+    ///Sets whether the time elapsed since the logger was initialized is shown instead of the time
+    ///stamp, e.g. `[   12.0345s INFO  app]`
+    ///
+    ///Makes it easier to correlate records with frame timings or benchmarks. The time is measured
+    ///from when the logger is enabled, or from the first record if it's used without being
+    ///enabled, with a monotonic clock outside the web. The time stamp format is ignored.
+    pub fn set_uptime_timestamps(&mut self, value: bool) {
+        self.uptime_timestamps = value;
+    }
+
+    ///Starts measuring the time shown by [`set_uptime_timestamps`](Self::set_uptime_timestamps)
+    fn start_uptime(&self) {
+        if self.uptime_timestamps {
+            let _ = self.uptime_start.set(monotonic_secs());
+        }
+    }
+
+    ///Sets an IANA time zone, e.g. `Europe/Berlin`, that is used for time stamps instead of the
+    ///local time zone
+    ///
+    ///Unlike a fixed offset, daylight saving time is applied. Useful in containers, where the
+    ///local time zone is usually UTC.
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the time zone is not known
+    #[cfg(feature = "timezone")]
+    pub fn set_timezone(&mut self, name: &str) -> Result<(), LoggerError> {
+        self.timezone = Zone::parse(name)?;
+        Ok(())
+    }
+
     ///Sets the default logging level, that filters everything that does not have a dedicated filter
     pub fn set_default_filter(&mut self, level: log::LevelFilter) {
-        self.default_level = level;
+        *self.default_level.get_mut() = level;
+    }
+
+    ///Sets the default level and updates the max level
+    fn update_default_level(&self, level: log::LevelFilter) {
+        *self.default_level.write() = level;
+        self.update_max_level();
     }
 
     ///Sets the logger will use color when logging
+    ///
+    ///By default color is only used if the console stream is a terminal, unless overridden with
+    ///the `NO_COLOR`, `CLICOLOR` or `CLICOLOR_FORCE` environment variables. Calling this takes
+    ///precedence over all of them.
     pub fn use_color(&mut self, value: bool) {
-        self.use_color = value;
+        self.set_color(value);
     }
-}
 
-fn create_file(path: &Path) -> Result<(), std::io::Error> {
-    let Some(parent) = path.parent() else {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "File is a directory",
-        ));
-    };
-    std::fs::create_dir_all(parent)?;
-    std::fs::File::create(path)?;
+    ///Sets the colors used in the console output
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
 
-    Ok(())
-}
+    ///Sets whether records are prefixed with a glyph of their level, e.g. `✖` for errors
+    pub fn set_level_glyphs(&mut self, value: bool) {
+        self.level_glyphs = value;
+    }
 
-fn generate_log_name() -> PathBuf {
-    //ISO-8601 time
-    let time = get_time("%Y-%m-%dT%H:%M:%S");
-    //TODO Think about windows
+    ///Sets whether records include the name of the thread they were logged from, or its ID if the
+    ///thread is unnamed
+    pub fn set_show_thread(&mut self, value: bool) {
+        self.show_thread = value;
+    }
 
-    let base_dir = if cfg!(target_os = "linux") {
-        if let Some((_, data)) = std::env::vars().find(|i| i.0 == "XDG_DATA_HOME") {
-            data
-        } else {
-            //TODO handle $HOME not being set
-            let home = std::env::vars().find(|i| i.0 == "HOME").unwrap().1;
-            format!("{home}/.local/share")
-        }
-    } else if cfg!(target_os = "windows") {
-        std::env::vars().find(|i| i.0 == "LOCALAPPDATA").unwrap().1
-    } else {
-        panic!("Unsupported platform")
-    };
+    ///Sets whether records include the ID of the process
+    pub fn set_show_pid(&mut self, value: bool) {
+        self.show_pid = value;
+    }
 
-    format!("{base_dir}/lunar-logging/log-{time}.log").into()
-}
+    ///Sets whether records include the host name of the machine
+    ///
+    ///The host name is read once, when the first record is logged. Does nothing on the web.
+    pub fn set_show_hostname(&mut self, value: bool) {
+        self.show_hostname = value;
+    }
 
-fn filter(filter: &str, filter_type: FilterType, data: &str) -> bool {
-    //crate_name::module::module::module:: ...
-    let mut split = data.split("::");
+    ///Sets the least verbose level of records that include the source file and line they were
+    ///logged from, e.g. `src/net/client.rs:42`
+    ///
+    ///With `Some(Level::Debug)` only `Debug` and `Trace` records include it, `None` disables it
+    pub fn set_show_location(&mut self, level: Option<log::Level>) {
+        self.show_location = level;
+    }
 
-    let crate_name = split.next().unwrap();
+    ///Sets the width the target is padded or truncated to in the text output, so that messages
+    ///line up
+    ///
+    ///Targets that are too long keep their end, e.g. `…::instance`. `None` disables it.
+    pub fn set_target_width(&mut self, width: Option<usize>) {
+        self.target_width = width;
+    }
 
-    match filter_type {
-        FilterType::Module => split.any(|x| x == filter),
-        FilterType::Crate => crate_name == filter,
+    ///Sets the label of the `level` used instead of the default, e.g. `WARNING` instead of `WARN`
+    ///
+    ///The label is used as is, default labels are padded to 5 characters
+    pub fn set_level_label(&mut self, level: log::Level, label: &str) {
+        self.level_labels.insert(level, label.to_owned());
     }
-}
 
-fn get_time(format: &str) -> String {
+    ///Sets the most verbose level written to the console
+    ///
+    ///Records still have to pass the filters, this only restricts the console further
+    pub fn set_console_level(&mut self, level: log::LevelFilter) {
+        self.console_level = level;
+    }
+
+    ///Sets the most verbose level written to the log file
+    ///
+    ///Records still have to pass the filters, this only restricts the log file further
+    pub fn set_file_level(&mut self, level: log::LevelFilter) {
+        self.file_level = level;
+    }
+
+    ///Sets the format of the console output
+    pub fn set_console_format(&mut self, format: Format) {
+        self.console_format = format;
+    }
+
+    ///Sets the format of the log file
+    pub fn set_file_format(&mut self, format: Format) {
+        self.file_format = format;
+    }
+
+    ///Overrides whether color is used
+    fn set_color(&self, value: bool) {
+        self.use_color.store(value, Ordering::Relaxed);
+        self.color_auto.store(false, Ordering::Relaxed);
+    }
+
+    ///Formats the record as a line of output
+    ///
+    ///Only the console output is colored and has glyphs, so that files stay plain text
+    fn format_line(&self, record: &FormattedRecord, console: bool) -> String {
+        let time = record.timestamp;
+        let msg = &format!("{}{}", format_context(record.context), record.message);
+        let level = record.level();
+        let level_str = self
+            .level_labels
+            .get(&level)
+            .map_or_else(|| format_level(level.to_level_filter()), String::as_str);
+        let glyph = if console && self.level_glyphs {
+            format!("{} ", level_glyph(level))
+        } else {
+            String::new()
+        };
+        let target = match self.target_width {
+            Some(width) => fit_width(record.target, width),
+            None => record.target.to_owned(),
+        };
+        let header: String = record
+            .fields
+            .iter()
+            .map(|(k, v)| format!(" {k}={v}"))
+            .collect();
+        let fields: String = record
+            .key_values
+            .iter()
+            .map(|(k, v)| format!(" {k}={v}"))
+            .collect();
+
+        #[allow(clippy::if_not_else)]
+        let plain = if !cfg!(target_arch = "wasm32") {
+            format!("{glyph}[{time} {level_str}{header} {target}] ")
+        } else {
+            format!("{glyph}[{time}{header} {target}] ")
+        };
+
+        //A stale value for a couple of records is fine
+        let prefix = if console && self.colored(level) {
+            let theme = &self.theme;
+            let depth = self.console.color_depth();
+            let brackets = Fg(theme.brackets, depth);
+            let timestamp = Fg(theme.timestamp, depth);
+            let color = Fg(theme.level(level), depth);
+            let target_color = Fg(theme.target, depth);
+            format!(
+                "{glyph}{brackets}[\x1b[0m{timestamp}{time} {color}{level_str}\x1b[0m{header} {target_color}{target}\x1b[0m{brackets}]\x1b[0m "
+            )
+        } else {
+            plain.clone()
+        };
+
+        let msg = match self.multiline_style {
+            MultilineStyle::Raw => msg.to_owned(),
+            MultilineStyle::Align => indent_lines(msg, &" ".repeat(plain.chars().count())),
+            MultilineStyle::Prefix => indent_lines(msg, &prefix),
+        };
+        format!("{prefix}{msg}{fields}\n")
+    }
+
+    ///Fields of the current record that are enabled on the logger, in the order they are displayed
+    fn record_fields(&self, record: &log::Record) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+
+        if self.show_hostname {
+            if let Some(hostname) = hostname() {
+                fields.push(("hostname", hostname.to_owned()));
+            }
+        }
+        if self.show_pid {
+            fields.push(("pid", std::process::id().to_string()));
+        }
+        if self.show_thread {
+            let thread = std::thread::current();
+            let name = thread.name().map_or_else(
+                || {
+                    //`ThreadId` only exposes its value through `Debug`, e.g. `ThreadId(2)`
+                    let id = format!("{:?}", thread.id());
+                    id.trim_start_matches("ThreadId(")
+                        .trim_end_matches(')')
+                        .to_owned()
+                },
+                str::to_owned,
+            );
+            fields.push(("thread", name));
+        }
+
+        if self
+            .show_location
+            .is_some_and(|level| record.level() >= level)
+        {
+            if let Some(file) = record.file() {
+                let location = match record.line() {
+                    Some(line) => format!("{file}:{line}"),
+                    None => file.to_owned(),
+                };
+                fields.push(("location", location));
+            }
+        }
+
+        fields
+    }
+
+    ///Checks whether a record of the `level` is colored
+    fn colored(&self, level: log::Level) -> bool {
+        let enabled = if self.color_auto.load(Ordering::Relaxed) {
+            self.console.supports_color(level)
+        } else {
+            self.use_color.load(Ordering::Relaxed)
+        };
+
+        //Falls back to no color if the console can't display it
+        enabled && self.console.supports_ansi(level)
+    }
+
+    ///Adds a prefix that is removed from the target when logging
+    ///
+    ///For example `my_crate::internal` turns `my_crate::internal::net::client` into
+    ///`net::client`. Prefixes are applied in the order they were added.
+    pub fn add_path_prefix_strip(&mut self, prefix: &str) {
+        self.path_prefix_strips.push(prefix.to_owned());
+    }
+
+    ///Sets whether targets are abbreviated to the first letter of every segment but the last
+    ///
+    ///For example `wgpu_hal::vulkan::instance` is shortened to `w::v::instance`. Applied after
+    ///the path prefixes are stripped.
+    pub fn set_abbreviate_targets(&mut self, value: bool) {
+        self.abbreviate_targets = value;
+    }
+
+    ///Sets whether writes to the log file are synchronized between processes
+    ///
+    ///When enabled, the file is opened in append mode and every write is done while holding an
+    ///exclusive advisory lock on the file. This allows multiple processes to safely log into the
+    ///same file, at the cost of some latency. The file is not truncated when the logger is enabled,
+    ///so records of processes that are already running are kept.
+    pub fn set_multi_process_safe(&mut self, value: bool) {
+        self.multi_process_safe = value;
+    }
+
+    ///Sets whether records are appended to existing log files instead of replacing their contents,
+    ///disabled by default
+    ///
+    ///By default, the log file and the error log file are truncated when the logger is enabled, so
+    ///every run starts with a fresh file. When enabled, records of previous runs are kept. Files
+    ///that are shared between processes are always appended to, see
+    ///[`set_multi_process_safe`](Self::set_multi_process_safe).
+    pub fn set_append_to_file(&mut self, value: bool) {
+        self.append_to_file = value;
+    }
+
+    ///Sets whether a header is written when the log file is opened, disabled by default
+    ///
+    ///The header contains the name and version of the app, the OS, the start time, the process ID
+    ///and the active filters, so every run in a file that is appended to can be told apart. The
+    ///name and version are taken from the `app` and `version` context fields if they are set, e.g.
+    ///by [`inject_cargo_metadata`], the name of the executable is used otherwise.
+    pub fn set_session_banner(&mut self, value: bool) {
+        self.session_banner = value;
+    }
+
+    ///Sets whether every line of the log file ends with a hash of the line before it, disabled by
+    ///default
+    ///
+    ///The lines form a chain that can be checked with [`verify_hash_chain`], so lines that were
+    ///modified, inserted or removed after they were written are detected. The hash is the first 8
+    ///bytes of the SHA-256 hash of the previous line, written as ` #<hex>`. A new file starts a new
+    ///chain, a file that is appended to continues the chain of its last line. Lines written by
+    ///other processes break the chain, so it should not be combined with
+    ///[`set_multi_process_safe`](Self::set_multi_process_safe).
+    #[cfg(feature = "audit")]
+    pub fn set_hash_chain(&mut self, value: bool) {
+        self.hash_chain = value;
+    }
+
+    ///Sets the permissions of the log files on Unix, e.g. `0o600` so only the owner can read them,
+    ///as records can contain sensitive data
+    ///
+    ///Applies to the log file, the error log file and rotated files. Existing files are changed
+    ///when they are opened. `None` uses the default permissions, limited by the umask.
+    #[cfg(unix)]
+    pub fn set_file_mode(&mut self, mode: Option<u32>) {
+        self.file_mode = mode;
+    }
+
+    ///Sets what the log file does when writing to it fails, `Retry` by default
+    ///
+    ///The error log file never falls back to another file. Writing to a stopped file is resumed
+    ///when the file is reopened.
+    pub fn set_write_failure_policy(&mut self, policy: WriteFailurePolicy) {
+        self.write_failure_policy = policy;
+    }
+
+    ///Registers a hook that is called with the formatted output of every logged record
+    ///
+    ///Hooks are called in the order they were registered, after the record was written. The output
+    ///is the same as written to the log file, in its [format](Self::set_file_format) and without
+    ///color codes.
+    pub fn register_output_hook(&mut self, f: OutputHook) {
+        self.output_hooks.push(Arc::from(f));
+    }
+
+    ///Sets a handler of errors that occur while logging, e.g. failed writes to the log file or
+    ///failed reloads of a watched file
+    ///
+    ///The handler must not log through the logger, as that could fail again. Without a handler
+    ///the errors are written to stderr. It's also passed to the sinks, so they report their
+    ///errors to it, see [`Sink::set_error_handler`].
+    pub fn set_on_error(&mut self, f: ErrorHandler) {
+        self.on_error = Some(Arc::from(f));
+        for sink in &self.sinks {
+            sink.set_error_handler(self.on_error.clone());
+        }
+    }
+
+    ///Returns the current size of the log file in bytes
+    ///
+    ///Returns `None` if logging to a file is disabled or if the size could not be queried
+    #[must_use]
+    pub fn current_file_size(&self) -> Option<u64> {
+        self.log_file.read().as_ref()?.size()
+    }
+
+    ///Sets whether every filter decision is printed to stderr
+    ///
+    ///Meant for debugging the filter configuration, e.g.
+    ///`FILTER: target=wgpu::hal level=Debug matched crate-filter[wgpu]=Warn → suppressed`
+    pub fn enable_filter_tracing(&mut self, value: bool) {
+        self.filter_tracing = value;
+    }
+
+    ///Sets the indent that is prepended to every line after the first in multi-line messages
+    pub fn set_multiline_indent(&mut self, indent: &str) {
+        self.multiline_indent = Some(indent.to_owned());
+    }
+
+    ///Sets how the lines after the first of multi-line messages are laid out in the text output
+    ///
+    ///Applied after the [multi-line indent](Self::set_multiline_indent)
+    pub fn set_multiline_style(&mut self, style: MultilineStyle) {
+        self.multiline_style = style;
+    }
+
+    ///Sets the maximum length of messages in characters, longer messages are cut off and end with
+    ///`…`
+    ///
+    ///The length includes the context fields. `None` disables it.
+    pub fn set_max_message_length(&mut self, length: Option<usize>) {
+        self.max_message_length = length;
+    }
+
+    ///Sets whether consecutive identical records are collapsed
+    ///
+    ///Only the first of the records is written, followed by `last message repeated N times` once a
+    ///different record is logged or the logger is flushed
+    pub fn set_suppress_duplicates(&mut self, value: bool) {
+        self.suppress_duplicates = value;
+    }
+
+    ///Samples `Debug` and `Trace` records of a module path and all of its submodules, e.g.
+    ///`my_app::render`
+    ///
+    ///Records are sampled after filtering, if multiple paths match the first one added is used
+    pub fn add_sampling(&mut self, path: &str, sampling: Sampling) {
+        self.samplers.push(Sampler::new(path.to_owned(), sampling));
+    }
+
+    ///Writes the `last message repeated N times` record, if the last record was repeated
+    fn flush_repeated(&self) {
+        let last = self.last_record.lock().unwrap().take();
+        self.write_repeated(last);
+    }
+
+    ///Writes the `last message repeated N times` record for `last`, if it was repeated
+    ///
+    ///Bypasses the duplicate suppression, as it must not replace the last record
+    fn write_repeated(&self, last: Option<LastRecord>) {
+        if let Some(((level, target, _), count)) = last.filter(|(_, count)| *count > 0) {
+            self.write_record(
+                &log::Record::builder()
+                    .level(level)
+                    .target(&target)
+                    .args(format_args!("last message repeated {count} times"))
+                    .build(),
+                false,
+            );
+        }
+    }
+
+    ///Adds a keyword that is replaced with `[REDACTED]` in every record, e.g. an API key
+    ///
+    ///Applies to the message, context fields and structured key-values, before they are written
+    ///anywhere
+    pub fn add_redacted_keyword(&mut self, keyword: &str) {
+        if !keyword.is_empty() {
+            self.redactions.push(Redaction::Keyword(keyword.to_owned()));
+        }
+    }
+
+    ///Adds a pattern whose matches are replaced with `[REDACTED]` in every record, e.g.
+    ///`Bearer [A-Za-z0-9._-]+`
+    ///
+    ///See [`add_redacted_keyword`](Self::add_redacted_keyword)
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the pattern is not a valid regex
+    #[cfg(feature = "regex")]
+    pub fn add_redaction_pattern(&mut self, pattern: &str) -> Result<(), LoggerError> {
+        let regex = regex::Regex::new(pattern).map_err(LoggerError::InvalidRegex)?;
+        self.redactions.push(Redaction::Pattern(regex));
+        Ok(())
+    }
+
+    ///Adds a context field that is included in every record
+    pub fn add_context(&mut self, key: &str, value: &str) {
+        self.context.push((key.to_owned(), value.to_owned()));
+    }
+
+    ///Sets whether the log file is synced to disk after every `Error` record
+    ///
+    ///Makes sure errors are not lost if the process crashes, at the cost of a syscall per error
+    pub fn set_force_flush_on_error(&mut self, value: bool) {
+        self.force_flush_on_error = value;
+    }
+
+    ///Sets when the log file is synced to disk, never by default
+    ///
+    ///[`SyncMode::EveryRecord`] makes the log crash-durable, e.g. for audit logs, at the cost of a
+    ///syscall per record. With [`SyncMode::Interval`] at most the records of the last interval are
+    ///lost. `Error` records are still synced if
+    ///[`set_force_flush_on_error`](Self::set_force_flush_on_error) is enabled.
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.sync_mode = mode;
+    }
+
+    ///Enables logging into an in-memory buffer and returns a handle to it
+    ///
+    ///Once the buffer holds `max_bytes`, the oldest lines are dropped to make room for new records,
+    ///a record that is larger than the buffer is dropped entirely. The handle stays valid after the
+    ///logger is enabled.
+    pub fn log_to_memory(&mut self, max_bytes: usize) -> Arc<Mutex<Vec<u8>>> {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        self.memory_buffer = Some(buffer.clone());
+        self.sinks.push(Arc::new(MemorySink {
+            buffer: buffer.clone(),
+            max_bytes,
+        }));
+        buffer
+    }
+
+    ///Keeps the last `capacity` formatted records in memory
+    ///
+    ///They can be retrieved with [`LoggerHandle::recent`], e.g. to show them in an in-game console
+    ///or attach them to a bug report
+    pub fn keep_recent(&mut self, capacity: usize) {
+        let records = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+        self.recent = Some(records.clone());
+        self.sinks.push(Arc::new(RecentSink { records, capacity }));
+    }
+
+    ///Keeps the last `capacity` `Debug` and `Trace` records in memory instead of writing them to
+    ///the log file, until an `Error` record is logged
+    ///
+    ///The buffered records are then written before the error, so failures come with full context
+    ///without the cost of always writing verbose records. `None` disables it.
+    pub fn set_buffer_until_error(&mut self, capacity: Option<usize>) {
+        self.error_buffer = capacity.map(|c| (Mutex::new(VecDeque::with_capacity(c)), c));
+    }
+
+    ///Buffers verbose records of the log file, and takes them out on errors
+    fn buffer_for_error(&self, record: &FormattedRecord) -> ErrorBuffer {
+        let Some((buffer, capacity)) = &self.error_buffer else {
+            return ErrorBuffer::Unbuffered;
+        };
+        let mut buffer = buffer.lock().unwrap();
+
+        match record.level() {
+            log::Level::Debug | log::Level::Trace => {
+                if *capacity > 0 {
+                    if buffer.len() == *capacity {
+                        buffer.pop_front();
+                    }
+                    buffer.push_back(record.output.to_owned());
+                }
+                ErrorBuffer::Buffered
+            }
+            log::Level::Error if !buffer.is_empty() => {
+                let mut output: String = buffer.drain(..).collect();
+                output.push_str(record.output);
+                ErrorBuffer::Dumped(output)
+            }
+            _ => ErrorBuffer::Unbuffered,
+        }
+    }
+
+    ///Adds a sink that receives every logged record
+    ///
+    ///Sinks are written to in the order they were added, after the console and the log file
+    pub fn add_sink(&mut self, sink: Box<dyn Sink>) {
+        self.add_shared_sink(Arc::from(sink));
+    }
+
+    ///Adds a sink that may be shared with other loggers, and passes it the error handler
+    fn add_shared_sink(&mut self, sink: Arc<dyn Sink>) {
+        sink.set_error_handler(self.on_error.clone());
+        self.sinks.push(sink);
+    }
+
+    ///Forwards every record that passes the filters to another logger, after it's handled
+    ///
+    ///Allows using another logger, e.g. of a crash reporter, alongside this one, since the [`log`]
+    ///crate only has a single global logger. The other logger is also flushed with this one. It
+    ///gets the record with the redactions applied to its message and key-values.
+    pub fn add_chained_logger(&mut self, logger: Box<dyn log::Log>) {
+        self.chained.push(Arc::from(logger));
+    }
+
+    ///Collects all formatted records into a vector and returns a handle to it
+    pub fn capture_to_vec(&mut self) -> Arc<Mutex<Vec<String>>> {
+        let capture = Arc::new(Mutex::new(Vec::new()));
+        self.sinks.push(Arc::new(CaptureSink(capture.clone())));
+        capture
+    }
+
+    ///Formats the record and writes it to the outputs, unless it's a duplicate of the last record
+    ///and `deduplicate` is set
+    fn write_record(&self, record: &log::Record, deduplicate: bool) {
+        let msg = record.args();
+        let metadata = record.metadata();
+        let target = metadata.target();
+
+        //Format:
+        //[TIMESTAMP TARGET LEVEL] MESSAGE
+        //
+
+        let target = self
+            .path_prefix_strips
+            .iter()
+            .fold(target, |target, prefix| strip_prefix(target, prefix));
+        let abbreviated;
+        let target = if self.abbreviate_targets {
+            abbreviated = abbreviate(target);
+            &abbreviated
+        } else {
+            target
+        };
+
+        let mut context = self.context.clone();
+        context.extend(context::fields());
+        for (_, value) in &mut context {
+            *value = redact::apply(&self.redactions, value);
+        }
+        let mut msg = msg.to_string();
+        if let Some(length) = self.max_message_length {
+            truncate_message(&mut msg, length);
+        }
+        if let Some(indent) = &self.multiline_indent {
+            msg = indent_lines(&msg, indent);
+        }
+
+        if deduplicate && self.suppress_duplicates {
+            let key = (record.level(), metadata.target().to_owned(), msg.clone());
+            //Compared and replaced under one lock, so concurrent duplicates are counted once each
+            let previous = {
+                let mut last = self.last_record.lock().unwrap();
+                match &mut *last {
+                    Some((last, count)) if *last == key => {
+                        *count += 1;
+                        return;
+                    }
+                    _ => last.replace((key, 0)),
+                }
+            };
+            self.write_repeated(previous);
+        }
+
+        let uptime = self
+            .uptime_timestamps
+            .then(|| *self.uptime_start.get_or_init(monotonic_secs));
+        let time = match (&self.timezone, uptime) {
+            (_, Some(start)) => format!("{:10.4}s", monotonic_secs() - start),
+            (Zone::Local, None) => get_time(&self.time_format),
+            (Zone::Fixed(offset), None) => get_time_with_offset(&self.time_format, offset),
+            #[cfg(feature = "timezone")]
+            (Zone::Named(zone), None) => get_time_with_offset(&self.time_format, zone),
+        };
+        let fields = self.record_fields(record);
+        let key_values = format::key_values(record);
+        let mut formatted = FormattedRecord {
+            record,
+            timestamp: &time,
+            target,
+            message: &msg,
+            context: &context,
+            fields: &fields,
+            key_values: &key_values,
+            output: "",
+        };
+        let output = self.format_line(&formatted, false);
+        formatted.output = &output;
+        //Output hooks get the same output as the log file
+        let rendered = self.file_format.render(&formatted);
+        let file_output = rendered.as_deref().unwrap_or(&output);
+
+        //Cloned out of the lock, as errors of the file are logged
+        let log_file = self
+            .log_file
+            .read()
+            .clone()
+            .filter(|_| record.level() <= self.file_level);
+        let error_file = self
+            .error_log_file
+            .get()
+            .filter(|_| record.level() <= log::Level::Warn);
+
+        if log_file.is_some() || error_file.is_some() {
+            if self.reopen_requested.swap(false, Ordering::Relaxed) {
+                let reopened = log_file.iter().map(AsRef::as_ref).chain(error_file);
+                for f in reopened {
+                    if let Err(e) = f.reopen() {
+                        report_error(self.on_error.as_ref(), LoggerError::FileError(e));
+                    }
+                }
+            }
+
+            let file_record = FormattedRecord {
+                output: file_output,
+                ..formatted
+            };
+            if let Some(f) = log_file {
+                match self.buffer_for_error(&file_record) {
+                    ErrorBuffer::Buffered => {}
+                    ErrorBuffer::Dumped(output) => f.write(&FormattedRecord {
+                        output: &output,
+                        ..file_record
+                    }),
+                    ErrorBuffer::Unbuffered => f.write(&file_record),
+                }
+            }
+            if let Some(f) = error_file {
+                f.write(&file_record);
+            }
+        }
+
+        if record.level() <= self.console_level {
+            //The console gets its own rendering with color and glyphs
+            let console_output = match &self.console_format {
+                Format::Text => (self.level_glyphs || self.colored(record.level()))
+                    .then(|| self.format_line(&formatted, true)),
+                format => format.render(&formatted),
+            };
+            self.console.write(&FormattedRecord {
+                output: console_output.as_deref().unwrap_or(&output),
+                ..formatted
+            });
+        }
+        for sink in &self.sinks {
+            sink.write(&formatted);
+        }
+
+        for hook in &self.output_hooks {
+            hook(file_output, record);
+        }
+
+        for logger in &self.chained {
+            logger.log(record);
+        }
+    }
+}
+
+///Returns the in-memory buffer of the program logger, if it has one
+///
+///See [`Logger::log_to_memory`]
+#[must_use]
+pub fn memory_sink() -> Option<Arc<Mutex<Vec<u8>>>> {
+    global_logger()?.memory_buffer.clone()
+}
+
+///Adds the name and version of the calling crate as context fields to a [Builder]
+///
+///The metadata is read with `env!` at the call site, so it belongs to the crate using the macro
+///rather than to `lunar-logger`.
+///
+///Example:
+///```
+///use lunar_logger::Builder;
+///
+///lunar_logger::inject_cargo_metadata!(Builder::new()).init().unwrap();
+///
+///log::info!("It works!");
+///```
+#[macro_export]
+macro_rules! inject_cargo_metadata {
+    ($builder:expr) => {
+        $builder
+            .add_context("app", env!("CARGO_PKG_NAME"))
+            .add_context("version", env!("CARGO_PKG_VERSION"))
+    };
+}
+
+///Disables the program logger
+///
+///All following records are discarded, until a new logger is enabled
+pub fn disable() {
+    log::set_max_level(log::LevelFilter::Off);
+    if let Some(logger) = INTERNAL_LOGGER.write().take() {
+        log::Log::flush(logger.as_ref());
+        //Handles may keep the logger alive, the watchers are stopped regardless
+        #[cfg(feature = "watch")]
+        logger.watchers.lock().unwrap().clear();
+    }
+}
+
+///Sets whether the program logger will use color
+///
+///Does nothing if the logger is not enabled
+pub fn set_color(value: bool) {
+    if let Some(logger) = global_logger() {
+        logger.set_color(value);
+    }
+}
+
+///Returns the current size of the log file of the program logger in bytes
+///
+///See [`Logger::current_file_size`]
+#[must_use]
+pub fn log_file_size() -> Option<u64> {
+    global_logger()?.current_file_size()
+}
+
+///Prints a table of the filters of the program logger to stderr
+///
+///Does nothing if the logger is not enabled
+pub fn display_filter_table() {
+    if let Some(logger) = global_logger() {
+        eprintln!("{}", logger.filter_table());
+    }
+}
+
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        log::error!(target: "panic", "{}", format_panic(info));
+        log::logger().flush();
+        previous(info);
+    }));
+}
+
+fn format_panic(info: &std::panic::PanicHookInfo) -> String {
+    let thread = std::thread::current();
+    let thread = thread.name().unwrap_or("<unnamed>");
+
+    let payload = info.payload();
+    let msg = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+
+    match info.location() {
+        Some(location) => format!("thread '{thread}' panicked at {location}:\n{msg}"),
+        None => format!("thread '{thread}' panicked:\n{msg}"),
+    }
+}
+
+///Creates the file and its directory if they don't exist, removing the contents of the file if
+///`truncate` is set
+fn create_file(path: &Path, mode: Option<u32>, truncate: bool) -> Result<(), std::io::Error> {
+    let Some(parent) = path.parent() else {
+        return Err(std::io::Error::other("File is a directory"));
+    };
+    std::fs::create_dir_all(parent)?;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(truncate);
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+    }
+    options.open(path)?;
+
+    Ok(())
+}
+
+///Points `latest.log` in the directory of the log file at it
+fn link_latest(path: &Path) -> std::io::Result<()> {
+    let link = path.with_file_name("latest.log");
+    if link == path {
+        return Ok(());
+    }
+
+    match std::fs::remove_file(&link) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    //Relative to the directory, so the link survives moving the directory
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(path.file_name().unwrap_or_default(), &link);
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_file(path, &link)
+        .or_else(|_| std::fs::hard_link(path, &link));
+    #[cfg(not(any(unix, windows)))]
+    Ok(())
+}
+
+///Directory of the default log file
+///
+///- Windows: `%LOCALAPPDATA%\lunar-logging`
+///- macOS: `~/Library/Logs/lunar-logging`
+///- Other platforms: `$XDG_DATA_HOME/lunar-logging` or `~/.local/share/lunar-logging`
+///
+///If the home directory can't be found, e.g. in containers without `HOME`, the temporary directory
+///or the current directory is used instead
+#[cfg(not(target_arch = "wasm32"))]
+fn default_log_dir() -> Option<PathBuf> {
+    let platform_dir = if cfg!(target_os = "macos") {
+        dirs::home_dir().map(|home| home.join("Library/Logs"))
+    } else {
+        dirs::data_local_dir()
+    };
+
+    log_dir_or_fallback(platform_dir)
+}
+
+///Falls back to the temporary directory and then the current directory if there's no directory
+#[cfg(not(target_arch = "wasm32"))]
+fn log_dir_or_fallback(dir: Option<PathBuf>) -> Option<PathBuf> {
+    let base_dir = dir
+        .or_else(|| Some(std::env::temp_dir()).filter(|d| d.is_dir()))
+        .or_else(|| std::env::current_dir().ok())?;
+
+    Some(base_dir.join("lunar-logging"))
+}
+
+///Generates the default log file name template, optionally including the ID of the process
+///
+///Returns `None` if there's no directory to put it in
+#[cfg(not(target_arch = "wasm32"))]
+fn generate_log_name(pid: bool) -> Option<PathBuf> {
+    let name = if pid {
+        "log-{datetime}-{pid}.log"
+    } else {
+        "log-{datetime}.log"
+    };
+    Some(default_log_dir()?.join(name))
+}
+
+///Expands the placeholders of a log file name template, see
+///[`set_log_file_name`](Logger::set_log_file_name)
+fn expand_file_name(template: &Path) -> PathBuf {
+    let Some(template) = template.to_str() else {
+        return template.to_owned();
+    };
+    if !template.contains('{') {
+        return template.into();
+    }
+
+    //ISO-8601 time, colons are not allowed in file names on Windows
+    let datetime = if cfg!(target_os = "windows") {
+        get_time("%Y-%m-%dT%H-%M-%S")
+    } else {
+        get_time("%Y-%m-%dT%H:%M:%S")
+    };
+
+    template
+        .replace("{app}", &app_name())
+        .replace("{datetime}", &datetime)
+        .replace("{date}", &get_time("%Y-%m-%d"))
+        .replace("{time}", &get_time("%H-%M-%S"))
+        .replace("{pid}", &std::process::id().to_string())
+        .replace("{hostname}", hostname().unwrap_or("unknown"))
+        .into()
+}
+
+///Name of the executable, used for the `{app}` placeholder
+fn app_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| Some(p.file_stem()?.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "app".into())
+}
+
+fn filter(filter: &str, filter_type: FilterType, data: &str) -> bool {
+    //crate_name::module::module::module:: ...
+    let mut split = data.split("::");
+
+    let crate_name = split.next().unwrap();
+
+    match filter_type {
+        FilterType::Module => split.any(|x| x == filter),
+        FilterType::Crate => crate_name == filter,
+        FilterType::Path => data
+            .strip_prefix(filter)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::")),
+    }
+}
+
+fn strip_prefix<'a>(target: &'a str, prefix: &str) -> &'a str {
+    target
+        .strip_prefix(prefix)
+        .and_then(|t| t.strip_prefix("::"))
+        .unwrap_or(target)
+}
+
+///Shortens every segment of the path but the last to its first character
+fn abbreviate(target: &str) -> String {
+    let mut segments: Vec<&str> = target.split("::").collect();
+    let last = segments.pop().unwrap_or_default();
+
+    let mut abbreviated = String::with_capacity(target.len());
+    for segment in segments {
+        abbreviated.extend(segment.chars().next());
+        abbreviated.push_str("::");
+    }
+    abbreviated.push_str(last);
+    abbreviated
+}
+
+///Pads the target with spaces or truncates it from the start to exactly `width` characters
+fn fit_width(target: &str, width: usize) -> String {
+    let len = target.chars().count();
+    if len <= width {
+        return format!("{target:width$}");
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let end: String = target.chars().skip(len - width + 1).collect();
+    format!("…{end}")
+}
+
+///Cuts the message off after `length` characters, marking it with `…`
+fn truncate_message(msg: &mut String, length: usize) {
+    if let Some((index, _)) = msg.char_indices().nth(length) {
+        msg.truncate(index);
+        msg.push('…');
+    }
+}
+
+///Prepends `indent` to every line after the first
+fn indent_lines(msg: &str, indent: &str) -> String {
+    msg.replace('\n', &format!("\n{indent}"))
+}
+
+///Formats context fields as a `[key=value ...] ` prefix
+pub(crate) fn format_context(context: &[(String, String)]) -> String {
+    if context.is_empty() {
+        return String::new();
+    }
+
+    let fields: Vec<String> = context.iter().map(|(k, v)| format!("{k}={v}")).collect();
+    format!("[{}] ", fields.join(" "))
+}
+
+fn get_time(format: &str) -> String {
     let time = chrono::Local::now();
     format!("{}", time.format(format))
 }
 
-const fn get_color(level: log::LevelFilter) -> &'static str {
+fn get_time_with_offset<Tz: chrono::TimeZone>(format: &str, zone: &Tz) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let time = chrono::Local::now().with_timezone(zone);
+    format!("{}", time.format(format))
+}
+
+///Seconds since an arbitrary point in time
+fn monotonic_secs() -> f64 {
+    //`Instant` is not available on the web
+    #[cfg(target_arch = "wasm32")]
+    return web_sys::js_sys::Date::now() / 1000.0;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static START: OnceLock<std::time::Instant> = OnceLock::new();
+        START
+            .get_or_init(std::time::Instant::now)
+            .elapsed()
+            .as_secs_f64()
+    }
+}
+
+///Severity of the level, as defined by RFC 5424
+pub(crate) const fn severity(level: log::Level) -> u8 {
     match level {
-        log::LevelFilter::Off => "",
-        log::LevelFilter::Error => "\x1b[31m",
-        log::LevelFilter::Warn => "\x1b[33m",
-        log::LevelFilter::Info => "\x1b[32m",
-        log::LevelFilter::Debug => "\x1b[35m",
-        log::LevelFilter::Trace => "\x1b[36m",
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    }
+}
+
+///Resolves `addr`, returning an error if it has no addresses
+pub(crate) fn resolve(
+    addr: impl std::net::ToSocketAddrs,
+) -> std::io::Result<Vec<std::net::SocketAddr>> {
+    let addrs: Vec<std::net::SocketAddr> = addr.to_socket_addrs()?.collect();
+    if addrs.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "No address to connect to",
+        ));
     }
+    Ok(addrs)
+}
+
+///Host name of the machine, cached after the first call
+fn hostname() -> Option<&'static str> {
+    static HOSTNAME: OnceLock<Option<String>> = OnceLock::new();
+    HOSTNAME.get_or_init(read_hostname).as_deref()
+}
+
+#[cfg(unix)]
+fn read_hostname() -> Option<String> {
+    //Host names are at most 255 bytes long
+    let mut buffer = [0u8; 256];
+
+    //SAFETY: the length passed is the length of the buffer
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    if result != 0 {
+        return None;
+    }
+
+    let len = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+    Some(String::from_utf8_lossy(&buffer[..len]).into_owned())
+}
+
+#[cfg(windows)]
+fn read_hostname() -> Option<String> {
+    use windows_sys::Win32::System::SystemInformation::{
+        ComputerNameDnsHostname, GetComputerNameExW,
+    };
+
+    let mut buffer = [0u16; 256];
+    let mut len = buffer.len() as u32;
+
+    //SAFETY: the length passed is the length of the buffer, it's updated to the length written
+    let result =
+        unsafe { GetComputerNameExW(ComputerNameDnsHostname, buffer.as_mut_ptr(), &mut len) };
+    if result == 0 {
+        return None;
+    }
+
+    Some(String::from_utf16_lossy(&buffer[..len as usize]))
+}
+
+#[cfg(not(any(unix, windows)))]
+const fn read_hostname() -> Option<String> {
+    None
 }
 
 const fn format_level(level: log::LevelFilter) -> &'static str {
@@ -264,6 +2169,17 @@ const fn format_level(level: log::LevelFilter) -> &'static str {
     }
 }
 
+///Glyph that is prepended to records when level glyphs are enabled
+const fn level_glyph(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "✖",
+        log::Level::Warn => "⚠",
+        log::Level::Info => "ℹ",
+        log::Level::Debug => "🐛",
+        log::Level::Trace => "•",
+    }
+}
+
 impl log::Log for Logger {
     fn enabled(&self, _: &log::Metadata) -> bool {
         true
@@ -275,72 +2191,140 @@ impl log::Log for Logger {
         let target = metadata.target();
         let msg_level = metadata.level().to_level_filter();
 
-        let mut filtered = false;
+        let (level, source) = self.target_level(target);
 
-        for (name, filter_type, level) in &self.filters {
-            if filter(name, *filter_type, target) {
-                //Test if the msg level msg is less severe than the filter level
-                if msg_level > *level {
-                    return;
-                }
-                filtered = true;
-                break;
-            }
+        //Written directly to stderr to avoid recursion
+        if self.filter_tracing {
+            eprintln!("{}", self.filter_trace(target, msg_level, level, source));
         }
 
-        //If not filtered and less severe than default level return
-        if !filtered && msg_level > self.default_level {
+        //Test if the msg level msg is less severe than the filter level
+        if msg_level > level {
             return;
         }
 
+        if record.level() >= log::Level::Debug {
+            let sampler = self.samplers.iter().find(|s| s.matches(target));
+            if sampler.is_some_and(|s| !s.keep()) {
+                return;
+            }
+        }
+
         //Passed all checks and can log stuff
+        if self.redactions.is_empty() {
+            self.write_record(record, true);
+        } else {
+            //Sinks, hooks and chained loggers only see the redacted record
+            redact::with_redacted(&self.redactions, record, |record| {
+                self.write_record(record, true);
+            });
+        }
+    }
 
-        //Format:
-        //[TIMESTAMP TARGET LEVEL] MESSAGE
-        //
+    fn flush(&self) {
+        if self.suppress_duplicates {
+            self.flush_repeated();
+        }
 
-        let time = get_time(&self.time_format);
-        let color = get_color(msg_level);
-        let msg_level_str = format_level(msg_level);
+        let log_file = self.log_file.read().clone();
+        if let Some(f) = log_file {
+            f.flush();
+        }
+        if let Some(f) = self.error_log_file.get() {
+            f.flush();
+        }
+        self.console.flush();
+        for sink in &self.sinks {
+            sink.flush();
+        }
+        for logger in &self.chained {
+            logger.flush();
+        }
+    }
+}
 
-        let output = if self.use_color {
-            format!(
-                "\x1b[90m[\x1b[0m{time} {color}{msg_level_str} \x1b[0m{target}\x1b[90m]\x1b[0m {msg}\n"
-            )
-        } else {
-            #[allow(clippy::collapsible_else_if, clippy::if_not_else)]
-            if !cfg!(target_arch = "wasm32") {
-                format!("[{time} {msg_level_str} {target}] {msg}\n")
-            } else {
-                format!("[{time} {target}] {msg}\n")
-            }
-        };
+///Logs every write as an `Info` record with the target `io_write`
+impl Write for Logger {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
 
-        if let Some(f) = &self.log_file {
-            if let Err(e) = f.write().unwrap().write(output.as_bytes()) {
-                log::error!("Failed to write to a file {e}");
-            }
+        let msg = String::from_utf8_lossy(buf);
+        let msg = msg.strip_suffix('\n').unwrap_or(&msg);
+
+        log::Log::log(
+            self,
+            &log::Record::builder()
+                .level(log::Level::Info)
+                .target("io_write")
+                .args(format_args!("{msg}"))
+                .build(),
+        );
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        log::Log::flush(self);
+        Ok(())
+    }
+}
+
+static INTERNAL_LOGGER: RwLock<Option<Arc<Logger>>> = RwLock::new(None);
+static PROXY_SET: AtomicBool = AtomicBool::new(false);
+
+///Returns the program logger, if it's enabled
+fn global_logger() -> Option<Arc<Logger>> {
+    INTERNAL_LOGGER.read().clone()
+}
+
+///Forwards records to the program logger, set with [`log::set_logger`]
+struct ProxyLogger;
+
+///Sets the proxy as the logger of the [`log`] crate, if it's not set yet
+///
+///The proxy can only be set once, but stays in place after the logger is disabled
+fn install_proxy() -> Result<(), LoggerError> {
+    static INSTALL: Mutex<()> = Mutex::new(());
+    let _lock = INSTALL.lock().unwrap();
+
+    if !PROXY_SET.load(Ordering::Relaxed) {
+        if log::set_logger(&ProxyLogger).is_err() {
+            return Err(LoggerError::LoggerAlreadySet);
         }
+        PROXY_SET.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
 
-        #[cfg(target_arch = "wasm32")]
-        {
-            use wasm_bindgen::JsValue;
-            use web_sys::console;
-
-            match msg_level {
-                log::LevelFilter::Off => {}
-                log::LevelFilter::Error => console::error_1(&JsValue::from_str(&output)),
-                log::LevelFilter::Warn => console::warn_1(&JsValue::from_str(&output)),
-                log::LevelFilter::Info => console::log_1(&JsValue::from_str(&output)),
-                log::LevelFilter::Debug => console::debug_1(&JsValue::from_str(&output)),
-                log::LevelFilter::Trace => console::trace_1(&JsValue::from_str(&output)),
+impl log::Log for ProxyLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        test::capturing() || global_logger().is_some_and(|l| l.enabled(metadata))
+    }
+
+    fn log(&self, record: &log::Record) {
+        //The lock is released before logging, so that the logger can be disabled from a hook
+        let logger = global_logger();
+
+        if test::capturing() {
+            //Captures must not contain what the logger would redact
+            match &logger {
+                Some(logger) if !logger.redactions.is_empty() => {
+                    redact::with_redacted(&logger.redactions, record, test::capture_record);
+                }
+                _ => test::capture_record(record),
             }
         }
-        #[cfg(not(target_arch = "wasm32"))]
-        print!("{output}");
+
+        if let Some(logger) = logger {
+            logger.log(record);
+        }
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        if let Some(logger) = global_logger() {
+            logger.flush();
+        }
+    }
 }
-
-static INTERNAL_LOGGER: OnceLock<Arc<Logger>> = OnceLock::new();
@@ -0,0 +1,171 @@
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+use crate::{
+    batch::Batcher, format::escape, http::Client, sink::SinkErrors, FormattedRecord, Sink,
+};
+
+///Labels, time stamp in nanoseconds and line of a record
+type Entry = (Vec<(String, String)>, i64, String);
+///Time stamps and lines of a stream
+type Values = Vec<(i64, String)>;
+
+///Sends records to Grafana Loki, using the push API
+///
+///Records are sent in batches from a background thread, once 100 records are collected or a
+///second passes. Every record has the `level` label, the `target` label unless disabled, and the
+///labels added with [`label`](LokiSinkBuilder::label). The lines are the records in the text
+///format.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, LokiSink};
+///
+///let loki = LokiSink::builder("https://logs.grafana.net")
+///    .basic_auth("123456", "api-key")
+///    .label("service", "my_app")
+///    .build();
+///
+///Builder::new().add_sink(Box::new(loki)).init().unwrap();
+///```
+pub struct LokiSink {
+    labels: Vec<(String, String)>,
+    target_label: bool,
+    batcher: Batcher<Entry>,
+    errors: SinkErrors,
+}
+
+///Builder of a [`LokiSink`]
+pub struct LokiSinkBuilder {
+    client: Client,
+    labels: Vec<(String, String)>,
+    target_label: bool,
+    max_batch: usize,
+    interval: Duration,
+}
+
+impl LokiSink {
+    ///Starts building a sink that pushes to the Loki server at `url`, e.g. `http://localhost:3100`
+    #[must_use]
+    pub fn builder(url: &str) -> LokiSinkBuilder {
+        let url = format!("{}/loki/api/v1/push", url.trim_end_matches('/'));
+        LokiSinkBuilder {
+            client: Client::new(&url),
+            labels: Vec::new(),
+            target_label: true,
+            max_batch: 100,
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl LokiSinkBuilder {
+    ///Adds a label to every record, e.g. the name of the service
+    #[must_use]
+    pub fn label(mut self, key: &str, value: &str) -> Self {
+        self.labels.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    ///Sets whether records have the `target` label, enabled by default
+    ///
+    ///Can be disabled if there are too many targets, as every label value creates a new stream
+    #[must_use]
+    pub const fn target_label(mut self, value: bool) -> Self {
+        self.target_label = value;
+        self
+    }
+
+    ///Authenticates with HTTP basic authentication, e.g. with the user ID and API key on Grafana
+    ///Cloud
+    #[must_use]
+    pub fn basic_auth(mut self, user: &str, password: &str) -> Self {
+        self.client.basic_auth(user, password);
+        self
+    }
+
+    ///Adds a header to the requests, e.g. `X-Scope-OrgID` for multi-tenant setups
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.client.header(name, value);
+        self
+    }
+
+    ///Sets the number of records that are sent at once, 100 by default
+    #[must_use]
+    pub const fn max_batch(mut self, records: usize) -> Self {
+        self.max_batch = records;
+        self
+    }
+
+    ///Sets how long records wait for a batch to fill before they are sent, a second by default
+    #[must_use]
+    pub const fn flush_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    ///Starts the background thread and creates the sink
+    #[must_use]
+    pub fn build(self) -> LokiSink {
+        let client = self.client;
+        let errors = client.errors.clone();
+        LokiSink {
+            labels: self.labels,
+            target_label: self.target_label,
+            batcher: Batcher::new(self.max_batch, self.interval, move |entries| {
+                client.post("application/json", push_body(entries).as_bytes());
+            }),
+            errors,
+        }
+    }
+}
+
+///Serializes the entries as a push request, with a stream for every set of labels
+pub(crate) fn push_body(entries: Vec<Entry>) -> String {
+    let mut streams: BTreeMap<Vec<(String, String)>, Values> = BTreeMap::new();
+    for (labels, time, line) in entries {
+        streams.entry(labels).or_default().push((time, line));
+    }
+
+    let streams: Vec<String> = streams
+        .iter()
+        .map(|(labels, values)| {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("\"{}\":\"{}\"", escape(k), escape(v)))
+                .collect();
+            let mut stream = format!("{{\"stream\":{{{}}},\"values\":[", labels.join(","));
+            for (i, (time, line)) in values.iter().enumerate() {
+                let separator = if i == 0 { "" } else { "," };
+                let _ = write!(stream, "{separator}[\"{time}\",\"{}\"]", escape(line));
+            }
+            stream.push_str("]}");
+            stream
+        })
+        .collect();
+
+    format!("{{\"streams\":[{}]}}", streams.join(","))
+}
+
+impl Sink for LokiSink {
+    fn write(&self, record: &FormattedRecord) {
+        let mut labels = self.labels.clone();
+        labels.push(("level".into(), record.level().as_str().to_lowercase()));
+        if self.target_label {
+            labels.push(("target".into(), record.target.to_owned()));
+        }
+        labels.sort();
+
+        let time = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let line = record.output.trim_end_matches('\n').to_owned();
+        self.batcher.push((labels, time, line));
+    }
+
+    fn flush(&self) {
+        self.batcher.flush();
+    }
+
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+        self.errors.set(handler);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::Mutex,
+};
+
+use crate::{resolve, sink::SinkErrors, tcp::TcpConnection, Format, FormattedRecord, Sink};
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpConnection),
+}
+
+///Streams formatted lines to a listener over TCP or UDP, e.g. `nc -l 5000` or a log collector
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, Format, NetworkSink};
+///
+///let sink = NetworkSink::tcp("192.168.1.10:5000")
+///    .unwrap()
+///    .format(Format::Json);
+///
+///Builder::new().add_sink(Box::new(sink)).init().unwrap();
+///```
+pub struct NetworkSink {
+    connection: Mutex<Connection>,
+    format: Format,
+    errors: SinkErrors,
+}
+
+impl NetworkSink {
+    ///Sends every line as a datagram to `addr`
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the address can't be resolved or the socket can't be created
+    pub fn udp(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let addr = resolve(addr)?[0];
+        let local: SocketAddr = if addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0; 16], 0).into()
+        };
+
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        Ok(Self {
+            connection: Mutex::new(Connection::Udp(socket)),
+            format: Format::Text,
+            errors: SinkErrors::default(),
+        })
+    }
+
+    ///Streams the lines over a TCP connection to `addr`
+    ///
+    ///If the connection is lost it is reestablished on the next record. If that fails, reconnecting
+    ///is retried after a delay that grows up to 30 seconds, the records in between are dropped.
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the address can't be resolved or the connection fails
+    pub fn tcp(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let connection = TcpConnection::connect(resolve(addr)?)?;
+        Ok(Self {
+            connection: Mutex::new(Connection::Tcp(connection)),
+            format: Format::Text,
+            errors: SinkErrors::default(),
+        })
+    }
+
+    ///Sets the format of the lines, plain text by default
+    #[must_use]
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+impl Sink for NetworkSink {
+    fn write(&self, record: &FormattedRecord) {
+        let rendered = self.format.render(record);
+        let line = rendered.as_deref().unwrap_or(record.output);
+        let result = match &mut *self.connection.lock().unwrap() {
+            Connection::Udp(socket) => socket.send(line.as_bytes()).map(|_| ()),
+            Connection::Tcp(connection) => connection.write_all(line.as_bytes()),
+        };
+
+        if let Err(e) = result {
+            self.errors.report("network sink", &e);
+        }
+    }
+
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+        self.errors.set(handler);
+    }
+}
@@ -0,0 +1,63 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use oslog::{Level, OsLog};
+
+use crate::{FormattedRecord, Sink};
+
+///Sends records to the Apple unified logging system, so they appear in Console.app
+///
+///Records are logged under the `subsystem`, usually the bundle identifier, with the target as the
+///category. `Error` records are logged as errors, `Warn` records with the default type, `Info`
+///records as info and `Debug` and `Trace` records as debug messages.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, OsLogSink};
+///
+///Builder::new()
+///    .add_sink(Box::new(OsLogSink::new("com.example.my_app")))
+///    .init()
+///    .unwrap();
+///```
+pub struct OsLogSink {
+    subsystem: String,
+    //Created for each category when it's first used
+    logs: Mutex<HashMap<String, OsLog>>,
+}
+
+impl OsLogSink {
+    ///Creates a sink that logs under the `subsystem`
+    #[must_use]
+    pub fn new(subsystem: &str) -> Self {
+        Self {
+            subsystem: subsystem.to_owned(),
+            logs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+const fn os_log_level(level: log::Level) -> Level {
+    match level {
+        log::Level::Error => Level::Error,
+        log::Level::Warn => Level::Default,
+        log::Level::Info => Level::Info,
+        log::Level::Debug | log::Level::Trace => Level::Debug,
+    }
+}
+
+impl Sink for OsLogSink {
+    fn write(&self, record: &FormattedRecord) {
+        let mut logs = self.logs.lock().unwrap();
+        let log = logs
+            .entry(record.target.to_owned())
+            .or_insert_with(|| OsLog::new(&self.subsystem, record.target));
+
+        //The unified logging system adds its own time stamp and level
+        let message = format!(
+            "{}{}",
+            crate::format_context(record.context),
+            record.message
+        );
+        log.with_level(os_log_level(record.level()), &message);
+    }
+}
@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use crate::{
+    batch::Batcher, format::escape, http::Client, sink::SinkErrors, FormattedRecord, Sink,
+};
+
+///Exports records to an OpenTelemetry collector, as OTLP log records over HTTP with JSON encoding
+///
+///Every record becomes a `LogRecord` with the severity of its level, the message as the body and
+///the `target`, `code.filepath` and `code.lineno` attributes, along with the fields enabled on the
+///logger and the structured key-values of the record. Records are sent in batches from a
+///background thread, once 100 records are collected or a second passes.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, OtlpSink};
+///
+///let otlp = OtlpSink::builder("http://localhost:4318")
+///    .service_name("my_app")
+///    .resource_attribute("deployment.environment", "production")
+///    .build();
+///
+///Builder::new().add_sink(Box::new(otlp)).init().unwrap();
+///```
+pub struct OtlpSink {
+    batcher: Batcher<String>,
+    errors: SinkErrors,
+}
+
+///Builder of an [`OtlpSink`]
+pub struct OtlpSinkBuilder {
+    client: Client,
+    resource: Vec<(String, String)>,
+    max_batch: usize,
+    interval: Duration,
+}
+
+impl OtlpSink {
+    ///Starts building a sink that exports to the collector at `endpoint`, e.g.
+    ///`http://localhost:4318`
+    #[must_use]
+    pub fn builder(endpoint: &str) -> OtlpSinkBuilder {
+        let url = format!("{}/v1/logs", endpoint.trim_end_matches('/'));
+        let service_name = std::env::current_exe()
+            .ok()
+            .and_then(|p| Some(p.file_stem()?.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "unknown_service".into());
+
+        OtlpSinkBuilder {
+            client: Client::new(&url),
+            resource: vec![("service.name".into(), service_name)],
+            max_batch: 100,
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl OtlpSinkBuilder {
+    ///Sets the `service.name` resource attribute, the name of the executable by default
+    #[must_use]
+    pub fn service_name(mut self, name: &str) -> Self {
+        name.clone_into(&mut self.resource[0].1);
+        self
+    }
+
+    ///Adds an attribute to the resource, e.g. `service.version`
+    #[must_use]
+    pub fn resource_attribute(mut self, key: &str, value: &str) -> Self {
+        self.resource.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    ///Adds a header to the requests, e.g. the API key of a hosted collector
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.client.header(name, value);
+        self
+    }
+
+    ///Sets the number of records that are sent at once, 100 by default
+    #[must_use]
+    pub const fn max_batch(mut self, records: usize) -> Self {
+        self.max_batch = records;
+        self
+    }
+
+    ///Sets how long records wait for a batch to fill before they are sent, a second by default
+    #[must_use]
+    pub const fn flush_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    ///Starts the background thread and creates the sink
+    #[must_use]
+    pub fn build(self) -> OtlpSink {
+        let client = self.client;
+        let errors = client.errors.clone();
+        let resource = attributes(self.resource.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        OtlpSink {
+            batcher: Batcher::new(self.max_batch, self.interval, move |records| {
+                client.post(
+                    "application/json",
+                    export_body(&resource, &records).as_bytes(),
+                );
+            }),
+            errors,
+        }
+    }
+}
+
+///Serializes the key-values as OTLP string attributes
+fn attributes<'a>(attributes: impl Iterator<Item = (&'a str, &'a str)>) -> String {
+    let attributes: Vec<String> = attributes.map(|(k, v)| string_attribute(k, v)).collect();
+    format!("[{}]", attributes.join(","))
+}
+
+///Serializes an attribute, `value` is the JSON of an OTLP `AnyValue`
+fn attribute(key: &str, value: &str) -> String {
+    format!("{{\"key\":\"{}\",\"value\":{value}}}", escape(key))
+}
+
+///Serializes an attribute with a string value
+fn string_attribute(key: &str, value: &str) -> String {
+    attribute(key, &format!("{{\"stringValue\":\"{}\"}}", escape(value)))
+}
+
+///Severity number of the level, as defined by the OpenTelemetry log data model
+const fn severity_number(level: log::Level) -> u8 {
+    match level {
+        log::Level::Trace => 1,
+        log::Level::Debug => 5,
+        log::Level::Info => 9,
+        log::Level::Warn => 13,
+        log::Level::Error => 17,
+    }
+}
+
+///Serializes the record as an OTLP `LogRecord`
+pub(crate) fn log_record(record: &FormattedRecord, time: i64) -> String {
+    let mut attributes: Vec<String> = std::iter::once(("target", record.target))
+        .chain(record.record.file().map(|file| ("code.filepath", file)))
+        .chain(record.fields.iter().map(|(k, v)| (*k, v.as_str())))
+        .chain(
+            record
+                .context
+                .iter()
+                .chain(record.key_values)
+                .map(|(k, v)| (k.as_str(), v.as_str())),
+        )
+        .map(|(k, v)| string_attribute(k, v))
+        .collect();
+    if let Some(line) = record.record.line() {
+        //Placed after `code.filepath`, 64 bit integers are strings in the JSON encoding
+        let index = 1 + usize::from(record.record.file().is_some());
+        attributes.insert(
+            index,
+            attribute("code.lineno", &format!("{{\"intValue\":\"{line}\"}}")),
+        );
+    }
+    let attributes = attributes.join(",");
+
+    format!(
+        "{{\"timeUnixNano\":\"{time}\",\"observedTimeUnixNano\":\"{time}\",\"severityNumber\":{},\"severityText\":\"{}\",\"body\":{{\"stringValue\":\"{}\"}},\"attributes\":[{attributes}]}}",
+        severity_number(record.level()),
+        record.level(),
+        escape(record.message),
+    )
+}
+
+///Serializes an export request with the log records, under a single resource and scope
+pub(crate) fn export_body(resource: &str, records: &[String]) -> String {
+    format!(
+        "{{\"resourceLogs\":[{{\"resource\":{{\"attributes\":{resource}}},\"scopeLogs\":[{{\"scope\":{{\"name\":\"{}\",\"version\":\"{}\"}},\"logRecords\":[{}]}}]}}]}}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        records.join(","),
+    )
+}
+
+impl Sink for OtlpSink {
+    fn write(&self, record: &FormattedRecord) {
+        let time = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        self.batcher.push(log_record(record, time));
+    }
+
+    fn flush(&self) {
+        self.batcher.flush();
+    }
+
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+        self.errors.set(handler);
+    }
+}
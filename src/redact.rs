@@ -0,0 +1,64 @@
+///Text that is masked in records
+#[derive(Clone)]
+pub(crate) enum Redaction {
+    Keyword(String),
+    #[cfg(feature = "regex")]
+    Pattern(regex::Regex),
+}
+
+///Text that replaces redacted values
+const MASK: &str = "[REDACTED]";
+
+///Masks all matches of the `redactions` in the `text`
+pub(crate) fn apply(redactions: &[Redaction], text: &str) -> String {
+    let mut text = text.to_owned();
+
+    for redaction in redactions {
+        text = match redaction {
+            Redaction::Keyword(keyword) => text.replace(keyword.as_str(), MASK),
+            #[cfg(feature = "regex")]
+            Redaction::Pattern(regex) => regex.replace_all(&text, MASK).into_owned(),
+        };
+    }
+
+    text
+}
+
+///Key-values of a record with the redactions applied
+struct KeyValues(Vec<(String, String)>);
+
+impl log::kv::Source for KeyValues {
+    fn visit<'kvs>(
+        &'kvs self,
+        visitor: &mut dyn log::kv::VisitSource<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        for (key, value) in &self.0 {
+            visitor.visit_pair(key.as_str().into(), value.as_str().into())?;
+        }
+        Ok(())
+    }
+}
+
+///Calls `f` with a copy of the record, with the redactions applied to its message and key-values
+pub(crate) fn with_redacted(
+    redactions: &[Redaction],
+    record: &log::Record,
+    f: impl FnOnce(&log::Record),
+) {
+    let message = apply(redactions, &record.args().to_string());
+    let key_values = KeyValues(
+        crate::format::key_values(record)
+            .into_iter()
+            .map(|(k, v)| (k, apply(redactions, &v)))
+            .collect(),
+    );
+
+    f(&log::Record::builder()
+        .args(format_args!("{message}"))
+        .metadata(record.metadata().clone())
+        .module_path(record.module_path())
+        .file(record.file())
+        .line(record.line())
+        .key_values(&key_values)
+        .build());
+}
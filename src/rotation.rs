@@ -0,0 +1,490 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+
+///Schedule for rotating log files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    ///Rotates the file at the start of every hour
+    Hourly,
+    ///Rotates the file at midnight
+    Daily,
+}
+
+impl Rotation {
+    ///Format of the time period covered by a single file
+    const fn period_format(self) -> &'static str {
+        match self {
+            Self::Hourly => "%Y-%m-%dT%H",
+            Self::Daily => "%Y-%m-%d",
+        }
+    }
+}
+
+///Describes how log files are rotated
+///
+///Example:
+///
+///```
+///use lunar_logger::FileRotationPolicy;
+///
+///let policy = FileRotationPolicy::new()
+///    .max_size(10 * 1024 * 1024)
+///    .naming_template("app-{date}-{index}.log");
+///```
+#[derive(Clone, Debug, Default)]
+pub struct FileRotationPolicy {
+    naming_template: Option<String>,
+    max_size: Option<u64>,
+    schedule: Option<Rotation>,
+    max_files: Option<usize>,
+    max_age: Option<Duration>,
+    #[cfg(feature = "gzip")]
+    compress: bool,
+}
+
+impl FileRotationPolicy {
+    ///Creates a new rotation policy
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            naming_template: None,
+            max_size: None,
+            schedule: None,
+            max_files: None,
+            max_age: None,
+            #[cfg(feature = "gzip")]
+            compress: false,
+        }
+    }
+
+    ///Rotates the log file once writing a record would make it larger than `bytes`
+    #[must_use]
+    pub const fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    ///Rotates the log file on a schedule
+    ///
+    ///The time is checked on every write, so the file is rotated by the first record logged in a
+    ///new period
+    #[must_use]
+    pub const fn schedule(mut self, rotation: Rotation) -> Self {
+        self.schedule = Some(rotation);
+        self
+    }
+
+    ///Keeps at most `n` rotated files, deleting the oldest ones after every rotation
+    #[must_use]
+    pub const fn max_rotated_files(mut self, n: usize) -> Self {
+        self.max_files = Some(n);
+        self
+    }
+
+    ///Deletes rotated files older than `age` after every rotation
+    #[must_use]
+    pub const fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    ///Sets whether rotated files are compressed with gzip
+    ///
+    ///Compression happens on a background thread, the compressed file gets a `.gz` extension
+    #[cfg(feature = "gzip")]
+    #[must_use]
+    pub const fn compress(mut self, value: bool) -> Self {
+        self.compress = value;
+        self
+    }
+
+    ///Sets the template used for naming rotated files
+    ///
+    ///Rotated files are placed in the same directory as the active log file. By default rotated
+    ///files are named by appending the index to the name of the active file, e.g. `app.log.1`,
+    ///or the period they cover when rotating on a schedule, e.g. `app.log.2024-01-15`. If the
+    ///template has no `{index}` and the file already exists, an index is appended to it.
+    ///
+    ///Supported placeholders:
+    /// - `{date}` - date of the rotation, `%Y-%m-%d`
+    /// - `{time}` - time of the rotation, `%H-%M-%S`
+    /// - `{index}` - rotation sequence number, starting at 1
+    /// - `{pid}` - id of the process
+    /// - `{exe}` - name of the executable
+    #[must_use]
+    pub fn naming_template(mut self, template: &str) -> Self {
+        self.naming_template = Some(template.to_owned());
+        self
+    }
+
+    ///Returns the path of the `index`th rotated file of the `active` log file
+    pub(crate) fn rotated_path(&self, active: &Path, index: usize) -> PathBuf {
+        let Some(template) = &self.naming_template else {
+            let mut name = active.as_os_str().to_owned();
+            name.push(format!(".{index}"));
+            return name.into();
+        };
+
+        let name = template
+            .replace("{date}", &crate::get_time("%Y-%m-%d"))
+            .replace("{time}", &crate::get_time("%H-%M-%S"))
+            .replace("{index}", &index.to_string())
+            .replace("{pid}", &std::process::id().to_string())
+            .replace("{exe}", &exe_name());
+
+        active.with_file_name(name)
+    }
+}
+
+fn exe_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .unwrap_or_default()
+}
+
+///Keeps track of rotations of a log file
+pub(crate) struct Rotator {
+    policy: FileRotationPolicy,
+    next_index: AtomicUsize,
+    //Period covered by the active file
+    pub(crate) period: Mutex<String>,
+    on_error: Option<crate::SharedErrorHandler>,
+}
+
+impl Rotator {
+    pub(crate) fn new(
+        policy: FileRotationPolicy,
+        on_error: Option<crate::SharedErrorHandler>,
+    ) -> Self {
+        let period = policy
+            .schedule
+            .map(|s| crate::get_time(s.period_format()))
+            .unwrap_or_default();
+
+        Self {
+            policy,
+            next_index: AtomicUsize::new(1),
+            period: Mutex::new(period),
+            on_error,
+        }
+    }
+
+    ///Checks whether the file has to be rotated before writing `len` more bytes into it
+    pub(crate) fn should_rotate(&self, size: u64, len: usize) -> bool {
+        let too_large = self
+            .policy
+            .max_size
+            .is_some_and(|max| size > 0 && size + len as u64 > max);
+
+        too_large
+            || self
+                .policy
+                .schedule
+                .is_some_and(|s| *self.period.lock().unwrap() != crate::get_time(s.period_format()))
+    }
+
+    ///Moves the `active` log file to the next free rotated path
+    pub(crate) fn rotate(&self, active: &Path) -> std::io::Result<PathBuf> {
+        let mut period = self.period.lock().unwrap();
+
+        let path = match (&self.policy.naming_template, self.policy.schedule) {
+            (None, Some(_)) => {
+                let mut name = active.as_os_str().to_owned();
+                name.push(format!(".{period}"));
+                free_path(name.into())
+            }
+            (Some(template), _) if !template.contains("{index}") => {
+                free_path(self.policy.rotated_path(active, 1))
+            }
+            _ => {
+                //Don't overwrite files from previous runs
+                let mut index = self.next_index.load(Ordering::Relaxed);
+                let mut path = self.policy.rotated_path(active, index);
+                while path.exists() {
+                    index += 1;
+                    path = self.policy.rotated_path(active, index);
+                }
+                self.next_index.store(index + 1, Ordering::Relaxed);
+                path
+            }
+        };
+
+        std::fs::rename(active, &path)?;
+
+        #[cfg(feature = "gzip")]
+        if self.policy.compress {
+            let path = path.clone();
+            let on_error = self.on_error.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = compress(&path) {
+                    let e = std::io::Error::new(
+                        e.kind(),
+                        format!("failed to compress {} {e}", path.display()),
+                    );
+                    crate::report_error(on_error.as_ref(), crate::LoggerError::RotationError(e));
+                }
+            });
+        }
+
+        if let Some(schedule) = self.policy.schedule {
+            *period = crate::get_time(schedule.period_format());
+        }
+        Ok(path)
+    }
+}
+
+impl Rotator {
+    ///Deletes rotated files according to the retention policy
+    pub(crate) fn prune(&self, active: &Path) -> std::io::Result<()> {
+        if self.policy.max_files.is_none() && self.policy.max_age.is_none() {
+            return Ok(());
+        }
+
+        let mut files = self.rotated_files(active)?;
+        //Newest first
+        files.sort_by_key(|f| std::cmp::Reverse(f.1));
+
+        let now = SystemTime::now();
+        for (i, (path, modified)) in files.iter().enumerate() {
+            let too_many = self.policy.max_files.is_some_and(|max| i >= max);
+            let too_old = self
+                .policy
+                .max_age
+                .is_some_and(|max| now.duration_since(*modified).is_ok_and(|age| age > max));
+
+            if too_many || too_old {
+                match std::fs::remove_file(path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Finds the rotated files of the `active` log file, with their modification time
+    fn rotated_files(&self, active: &Path) -> std::io::Result<Vec<(PathBuf, SystemTime)>> {
+        let Some(active_name) = active.file_name().and_then(|n| n.to_str()) else {
+            return Ok(Vec::new());
+        };
+
+        //Without a template rotated files are named after the active file
+        let pattern = match &self.policy.naming_template {
+            Some(template) => name_pattern(template),
+            None => vec![NamePart::Literal(active_name.to_owned())],
+        };
+
+        let dir = match active.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+
+            if name == active_name || matches_run(name, &pattern).is_none() {
+                continue;
+            }
+
+            files.push((entry.path(), entry.metadata()?.modified()?));
+        }
+
+        Ok(files)
+    }
+}
+
+///Deletes the log files of previous runs that were created from the filename `template`, keeping
+///the `keep_last` newest runs, including the `active` one, that are not older than `max_age`
+///
+///Rotated and compressed files of a run, e.g. `app.log.1.gz`, are deleted along with it. Entries
+///that can't be read are skipped.
+pub(crate) fn prune_logs(
+    template: &Path,
+    active: &Path,
+    keep_last: usize,
+    max_age: Duration,
+) -> std::io::Result<()> {
+    let Some(template) = template.file_name().and_then(|n| n.to_str()) else {
+        return Ok(());
+    };
+    //Without placeholders every run uses the same file
+    if !template.contains('{') {
+        return Ok(());
+    }
+    let Some(active_name) = active.file_name().and_then(|n| n.to_str()) else {
+        return Ok(());
+    };
+    let pattern = name_pattern(template);
+
+    let dir = match active.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+
+    //Files of every previous run, grouped by the name of its log file
+    let mut runs: HashMap<String, Vec<(PathBuf, SystemTime)>> = HashMap::new();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let Some(run) = matches_run(name, &pattern) else {
+            continue;
+        };
+        //The active file and its rotated files are handled by the rotation
+        if run == active_name {
+            continue;
+        }
+        let metadata = entry.metadata().ok().filter(std::fs::Metadata::is_file);
+        let Some(modified) = metadata.and_then(|m| m.modified().ok()) else {
+            continue;
+        };
+
+        runs.entry(run.to_owned())
+            .or_default()
+            .push((entry.path(), modified));
+    }
+    //Newest first, by the newest file of every run
+    let mut runs: Vec<_> = runs.into_values().collect();
+    runs.sort_by_key(|files| std::cmp::Reverse(files.iter().map(|f| f.1).max()));
+
+    let now = SystemTime::now();
+    let mut result = Ok(());
+    for (i, files) in runs.iter().enumerate() {
+        let too_many = i + 1 >= keep_last;
+
+        for (path, modified) in files {
+            let too_old = now.duration_since(*modified).is_ok_and(|age| age > max_age);
+            if too_many || too_old {
+                match std::fs::remove_file(path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => result = Err(e),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    result
+}
+
+///Part of a log file name template
+enum NamePart {
+    Literal(String),
+    ///Digits in the places of `#`, other characters as is
+    Shape(String),
+    ///One or more digits
+    Number,
+}
+
+///Splits a log file name or rotated file naming template into the parts a name created from it
+///consists of
+fn name_pattern(template: &str) -> Vec<NamePart> {
+    //Same separators as the expanded names
+    let datetime = if cfg!(target_os = "windows") {
+        "####-##-##T##-##-##"
+    } else {
+        "####-##-##T##:##:##"
+    };
+
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        parts.push(NamePart::Literal(rest[..start].to_owned()));
+        parts.push(match &rest[start..=start + len] {
+            "{app}" => NamePart::Literal(crate::app_name()),
+            "{hostname}" => NamePart::Literal(crate::hostname().unwrap_or("unknown").to_owned()),
+            "{date}" => NamePart::Shape("####-##-##".to_owned()),
+            "{time}" => NamePart::Shape("##-##-##".to_owned()),
+            "{datetime}" => NamePart::Shape(datetime.to_owned()),
+            "{pid}" | "{index}" => NamePart::Number,
+            "{exe}" => NamePart::Literal(exe_name()),
+            other => NamePart::Literal(other.to_owned()),
+        });
+        rest = &rest[start + len + 1..];
+    }
+    parts.push(NamePart::Literal(rest.to_owned()));
+    parts
+}
+
+///Returns the name of the log file `name` belongs to, if it was created from the `pattern`, or
+///was rotated or compressed from such a file
+fn matches_run<'a>(name: &'a str, pattern: &[NamePart]) -> Option<&'a str> {
+    let mut rest = name;
+    for part in pattern {
+        rest = match part {
+            NamePart::Literal(literal) => rest.strip_prefix(literal.as_str())?,
+            NamePart::Shape(shape) => {
+                let (head, tail) = (rest.get(..shape.len())?, &rest[shape.len()..]);
+                let matches = head.chars().zip(shape.chars()).all(|(c, s)| match s {
+                    '#' => c.is_ascii_digit(),
+                    s => c == s,
+                });
+                matches.then_some(tail)?
+            }
+            NamePart::Number => {
+                let tail = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+                (tail.len() < rest.len()).then_some(tail)?
+            }
+        };
+    }
+
+    //Rotated files end with an index or period, compressed ones with `.gz`
+    let run = &name[..name.len() - rest.len()];
+    let rotated = rest.split('.').skip(1).all(|s| {
+        s == "gz"
+            || (!s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_ascii_digit() || c == '-' || c == 'T'))
+    });
+    (rest.is_empty() || (rest.starts_with('.') && rotated)).then_some(run)
+}
+
+///Compresses the file into `<path>.gz` and removes the original
+#[cfg(feature = "gzip")]
+fn compress(path: &Path) -> std::io::Result<()> {
+    let mut gz_path = path.as_os_str().to_owned();
+    gz_path.push(".gz");
+
+    let mut input = std::fs::File::open(path)?;
+    let output = std::fs::File::create(gz_path)?;
+    //Keeps the permissions of the log file, which may be restricted
+    output.set_permissions(input.metadata()?.permissions())?;
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+
+    std::fs::remove_file(path)
+}
+
+///Appends an index to the path if it already exists
+fn free_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+
+    (1..)
+        .map(|i| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(format!(".{i}"));
+            PathBuf::from(name)
+        })
+        .find(|p| !p.exists())
+        .unwrap()
+}
@@ -0,0 +1,80 @@
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::FilterType;
+
+///How `Debug` and `Trace` records of a target are sampled
+///
+///Example:
+///```
+///use lunar_logger::{Builder, Sampling};
+///
+///Builder::new()
+///    .default_filter(log::LevelFilter::Trace)
+///    .sample("my_app::render", Sampling::EveryNth(100))
+///    .sample("my_app::physics", Sampling::Probability(0.01))
+///    .init()
+///    .unwrap();
+///```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    ///Keeps every `n`th record, starting with the first
+    EveryNth(u64),
+    ///Keeps each record with the probability, between `0.0` and `1.0`
+    Probability(f64),
+}
+
+///Sampling of a module path and all of its submodules
+pub(crate) struct Sampler {
+    path: String,
+    sampling: Sampling,
+    counter: AtomicU64,
+}
+
+impl Sampler {
+    pub(crate) const fn new(path: String, sampling: Sampling) -> Self {
+        Self {
+            path,
+            sampling,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn matches(&self, target: &str) -> bool {
+        crate::filter(&self.path, FilterType::Path, target)
+    }
+
+    ///Decides whether the next record is kept
+    pub(crate) fn keep(&self) -> bool {
+        match self.sampling {
+            Sampling::EveryNth(n) => {
+                let count = self.counter.fetch_add(1, Ordering::Relaxed);
+                count.is_multiple_of(n.max(1))
+            }
+            Sampling::Probability(p) => random() < p,
+        }
+    }
+}
+
+///Random number in `[0, 1)`, from a xorshift generator of the current thread
+fn random() -> f64 {
+    thread_local! {
+        //Must not be 0
+        static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+    }
+
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+
+        //The top 53 bits fit exactly in the mantissa
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
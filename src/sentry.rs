@@ -0,0 +1,141 @@
+use log::LevelFilter;
+use sentry_core::protocol::{Breadcrumb, Event, Level, Map, Value};
+
+use crate::{FormattedRecord, Sink};
+
+///Function that modifies or drops an event before it's sent to Sentry
+pub type SentryHook =
+    Box<dyn Fn(&FormattedRecord, Event<'static>) -> Option<Event<'static>> + Send + Sync>;
+
+///Forwards records to Sentry, using the hub of the current thread
+///
+///`Error` records are captured as events, records of lower levels down to `Info` are added as
+///breadcrumbs, so the events come with the records that were logged before them. The Sentry client
+///has to be initialized separately, e.g. with `sentry::init`.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, SentrySink};
+///
+///let sentry = SentrySink::new()
+///    .event_level(log::LevelFilter::Warn)
+///    .before_send(Box::new(|_, mut event| {
+///        event.tags.insert("component".into(), "renderer".into());
+///        Some(event)
+///    }));
+///
+///Builder::new().add_sink(Box::new(sentry)).init().unwrap();
+///```
+pub struct SentrySink {
+    event_level: LevelFilter,
+    breadcrumb_level: LevelFilter,
+    before_send: Option<SentryHook>,
+}
+
+impl Default for SentrySink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SentrySink {
+    ///Creates a sink that captures `Error` records and adds `Warn` and `Info` records as
+    ///breadcrumbs
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            event_level: LevelFilter::Error,
+            breadcrumb_level: LevelFilter::Info,
+            before_send: None,
+        }
+    }
+
+    ///Sets the lowest level of records that are captured as events, `Error` by default
+    #[must_use]
+    pub const fn event_level(mut self, level: LevelFilter) -> Self {
+        self.event_level = level;
+        self
+    }
+
+    ///Sets the lowest level of records that are added as breadcrumbs, `Info` by default
+    ///
+    ///Records captured as events are not added as breadcrumbs
+    #[must_use]
+    pub const fn breadcrumb_level(mut self, level: LevelFilter) -> Self {
+        self.breadcrumb_level = level;
+        self
+    }
+
+    ///Sets a hook that is called with every event before it's captured, returning `None` drops the
+    ///event
+    #[must_use]
+    pub fn before_send(mut self, f: SentryHook) -> Self {
+        self.before_send = Some(f);
+        self
+    }
+}
+
+const fn sentry_level(level: log::Level) -> Level {
+    match level {
+        log::Level::Error => Level::Error,
+        log::Level::Warn => Level::Warning,
+        log::Level::Info => Level::Info,
+        log::Level::Debug | log::Level::Trace => Level::Debug,
+    }
+}
+
+///Converts the record to an event, with the context fields and key-values as extra data and the
+///fields enabled on the logger as tags
+pub(crate) fn event(record: &FormattedRecord) -> Event<'static> {
+    let mut event = Event {
+        level: sentry_level(record.level()),
+        logger: Some(record.target.to_owned()),
+        message: Some(record.message.to_owned()),
+        ..Event::default()
+    };
+
+    for (key, value) in record.fields {
+        event.tags.insert((*key).to_owned(), value.clone());
+    }
+    for (key, value) in record.context.iter().chain(record.key_values) {
+        event.extra.insert(key.clone(), Value::from(value.as_str()));
+    }
+    event
+}
+
+///Converts the record to a breadcrumb, with the context fields and key-values as data
+pub(crate) fn breadcrumb(record: &FormattedRecord) -> Breadcrumb {
+    let data: Map<String, Value> = record
+        .context
+        .iter()
+        .chain(record.key_values)
+        .map(|(k, v)| (k.clone(), Value::from(v.as_str())))
+        .collect();
+
+    Breadcrumb {
+        ty: "log".into(),
+        category: Some(record.target.to_owned()),
+        level: sentry_level(record.level()),
+        message: Some(record.message.to_owned()),
+        data,
+        ..Breadcrumb::default()
+    }
+}
+
+impl Sink for SentrySink {
+    fn write(&self, record: &FormattedRecord) {
+        let level = record.level();
+
+        if level <= self.event_level {
+            let mut event = Some(event(record));
+            if let Some(f) = &self.before_send {
+                event = event.and_then(|e| f(record, e));
+            }
+            if let Some(event) = event {
+                sentry_core::capture_event(event);
+            }
+        } else if level <= self.breadcrumb_level {
+            sentry_core::add_breadcrumb(breadcrumb(record));
+        }
+    }
+}
@@ -0,0 +1,656 @@
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    io::{IsTerminal, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Instant,
+};
+
+use crate::{rotation::Rotator, sync::RwLock, theme::ColorDepth, FileRotationPolicy, LoggerError};
+
+///A record that passed all filters and was formatted by the [Logger](crate::Logger)
+pub struct FormattedRecord<'a> {
+    ///The original record, with the redactions applied to its message and key-values
+    pub record: &'a log::Record<'a>,
+    ///The formatted time stamp
+    pub timestamp: &'a str,
+    ///The target, with prefixes stripped
+    pub target: &'a str,
+    ///The message, with redactions applied
+    pub message: &'a str,
+    ///Context fields of the logger and the current scope, with redactions applied
+    pub context: &'a [(String, String)],
+    ///Fields enabled on the logger, such as the thread name, in the order they are displayed
+    pub fields: &'a [(&'static str, String)],
+    ///Structured key-values of the record, with redactions applied
+    pub key_values: &'a [(String, String)],
+    ///The fully formatted line, ending with a newline
+    ///
+    ///Only the console output contains color codes and level glyphs
+    pub output: &'a str,
+}
+
+impl FormattedRecord<'_> {
+    ///Level of the record
+    #[must_use]
+    pub fn level(&self) -> log::Level {
+        self.record.level()
+    }
+}
+
+///Destination of formatted records
+///
+///Example:
+///
+///```
+///use lunar_logger::{Builder, FormattedRecord, Sink};
+///
+///struct Stderr;
+///
+///impl Sink for Stderr {
+///    fn write(&self, record: &FormattedRecord) {
+///        eprint!("{}", record.output);
+///    }
+///}
+///
+///Builder::new().add_sink(Box::new(Stderr)).init().unwrap();
+///
+///log::info!("It works!");
+///```
+pub trait Sink: Send + Sync {
+    ///Writes a record
+    fn write(&self, record: &FormattedRecord);
+
+    ///Flushes any buffered records
+    fn flush(&self) {}
+
+    ///Receives the error handler of the logger the sink is added to, whenever it's set
+    ///
+    ///Sinks should report their errors to it, or write them to stderr without one, see
+    ///[`set_on_error`](crate::Logger::set_on_error)
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {}
+}
+
+///Error handler of a sink, shared with its background threads
+#[derive(Clone, Default)]
+pub(crate) struct SinkErrors(Arc<Mutex<Option<crate::SharedErrorHandler>>>);
+
+impl SinkErrors {
+    pub(crate) fn set(&self, handler: Option<crate::SharedErrorHandler>) {
+        *self.0.lock().unwrap() = handler;
+    }
+
+    ///Reports the error of the `sink` to the handler, or to stderr without one
+    pub(crate) fn report(&self, sink: &str, error: &std::io::Error) {
+        let error = std::io::Error::new(error.kind(), format!("{sink}: {error}"));
+        //Cloned out of the lock, as the handler could take a while
+        let handler = self.0.lock().unwrap().clone();
+        crate::report_error(handler.as_ref(), LoggerError::SinkError(error));
+    }
+}
+
+///Stream the console output is written to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConsoleStream {
+    ///Standard output
+    #[default]
+    Stdout,
+    ///Standard error
+    Stderr,
+}
+
+///Writes records to the console
+#[derive(Default)]
+pub(crate) struct ConsoleSink {
+    pub(crate) stream: ConsoleStream,
+    ///Whether `Warn` and `Error` records are written to stderr regardless of the stream
+    pub(crate) warnings_to_stderr: bool,
+    //Queried once, as it's a syscall
+    stdout_terminal: OnceLock<bool>,
+    stderr_terminal: OnceLock<bool>,
+    env_color: OnceLock<Option<bool>>,
+    color_depth: OnceLock<ColorDepth>,
+    #[cfg(windows)]
+    stdout_ansi: OnceLock<bool>,
+    #[cfg(windows)]
+    stderr_ansi: OnceLock<bool>,
+}
+
+impl ConsoleSink {
+    ///Stream a record of the `level` is written to
+    fn stream(&self, level: log::Level) -> ConsoleStream {
+        if self.warnings_to_stderr && level <= log::Level::Warn {
+            ConsoleStream::Stderr
+        } else {
+            self.stream
+        }
+    }
+
+    ///Colors supported by the terminal
+    pub(crate) fn color_depth(&self) -> ColorDepth {
+        *self
+            .color_depth
+            .get_or_init(|| ColorDepth::detect(|var| std::env::var(var).ok()))
+    }
+
+    ///Checks whether ANSI codes can be written to the stream of a record of the `level`
+    ///
+    ///On Windows this enables virtual terminal processing on the console
+    pub(crate) fn supports_ansi(&self, level: log::Level) -> bool {
+        #[cfg(windows)]
+        return match self.stream(level) {
+            ConsoleStream::Stdout => *self
+                .stdout_ansi
+                .get_or_init(|| enable_virtual_terminal(ConsoleStream::Stdout)),
+            ConsoleStream::Stderr => *self
+                .stderr_ansi
+                .get_or_init(|| enable_virtual_terminal(ConsoleStream::Stderr)),
+        };
+
+        #[cfg(not(windows))]
+        true
+    }
+
+    ///Checks whether a record of the `level` should be colored
+    ///
+    ///The `CLICOLOR_FORCE`, `NO_COLOR` and `CLICOLOR` environment variables are checked first,
+    ///then whether the stream is a terminal
+    pub(crate) fn supports_color(&self, level: log::Level) -> bool {
+        if let Some(value) = *self
+            .env_color
+            .get_or_init(|| color_from_env(|var| std::env::var(var).ok()))
+        {
+            return value;
+        }
+
+        match self.stream(level) {
+            ConsoleStream::Stdout => *self
+                .stdout_terminal
+                .get_or_init(|| std::io::stdout().is_terminal()),
+            ConsoleStream::Stderr => *self
+                .stderr_terminal
+                .get_or_init(|| std::io::stderr().is_terminal()),
+        }
+    }
+}
+
+impl Sink for ConsoleSink {
+    fn write(&self, record: &FormattedRecord) {
+        let output = record.output;
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use wasm_bindgen::JsValue;
+            use web_sys::console;
+
+            //Every call is a separate entry in the devtools, so the newline would add an empty line
+            let output = output.trim_end_matches('\n');
+            match record.level() {
+                log::Level::Error => console::error_1(&JsValue::from_str(output)),
+                log::Level::Warn => console::warn_1(&JsValue::from_str(output)),
+                log::Level::Info => console::info_1(&JsValue::from_str(output)),
+                log::Level::Debug => console::debug_1(&JsValue::from_str(output)),
+                log::Level::Trace => console::trace_1(&JsValue::from_str(output)),
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        match self.stream(record.level()) {
+            ConsoleStream::Stdout => print!("{output}"),
+            ConsoleStream::Stderr => eprint!("{output}"),
+        }
+    }
+
+    fn flush(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = std::io::stdout().flush();
+    }
+}
+
+///Enables processing of ANSI codes by the console of the `stream`
+///
+///Returns `false` if the console doesn't support it. Streams that are not a console, e.g. files or
+///pipes, are left as is.
+#[cfg(windows)]
+fn enable_virtual_terminal(stream: ConsoleStream) -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_ERROR_HANDLE, STD_OUTPUT_HANDLE,
+    };
+
+    let handle = match stream {
+        ConsoleStream::Stdout => STD_OUTPUT_HANDLE,
+        ConsoleStream::Stderr => STD_ERROR_HANDLE,
+    };
+
+    //SAFETY: the handle is only passed to console functions, which fail on invalid handles
+    unsafe {
+        let handle = GetStdHandle(handle);
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return true;
+        }
+        if mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 {
+            return true;
+        }
+        SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+///Decides whether to use color based on the `CLICOLOR_FORCE`, `NO_COLOR` and `CLICOLOR`
+///conventions, `None` if they don't decide it
+pub(crate) fn color_from_env(var: impl Fn(&str) -> Option<String>) -> Option<bool> {
+    if var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        return Some(true);
+    }
+    if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return Some(false);
+    }
+    if var("CLICOLOR").is_some_and(|v| v == "0") {
+        return Some(false);
+    }
+    None
+}
+
+///What the log file does when writing to it fails, e.g. because the disk is full or the file was
+///deleted
+///
+///The failure is reported once, as a [`LoggerError::WriteError`] to the
+///[`on_error`](crate::Logger::set_on_error) handler, or to stderr without one, instead of for
+///every record.
+///
+///Example:
+///```
+///use lunar_logger::{Builder, WriteFailurePolicy};
+///
+///Builder::new()
+///    .log_to_file()
+///    .write_failure_policy(WriteFailurePolicy::Fallback(std::env::temp_dir().join("app.log")))
+///    .create();
+///```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum WriteFailurePolicy {
+    ///Keeps writing to the file, the failure is reported again after a write succeeds
+    #[default]
+    Retry,
+    ///Stops writing to the file, records are still written to the console and the sinks
+    Disable,
+    ///Opens the file at the path and writes to it instead, if that fails as well writing to files
+    ///is stopped
+    Fallback(PathBuf),
+}
+
+///When the log file is synced to disk
+///
+///Syncing makes sure the records are not lost if the system crashes, at the cost of throughput
+///
+///Example:
+///```
+///use std::time::Duration;
+///use lunar_logger::{Builder, SyncMode};
+///
+///Builder::new()
+///    .log_to_file()
+///    .sync_mode(SyncMode::Interval(Duration::from_secs(1)))
+///    .create();
+///```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    ///Syncs after every record
+    EveryRecord,
+    ///Syncs after a record if the interval passed since the last sync
+    Interval(std::time::Duration),
+    ///Leaves syncing to the operating system
+    #[default]
+    Never,
+}
+
+///Options of the log files
+#[derive(Clone, Default)]
+pub(crate) struct FileOptions {
+    pub(crate) multi_process_safe: bool,
+    pub(crate) force_flush_on_error: bool,
+    pub(crate) sync_mode: SyncMode,
+    #[cfg(feature = "audit")]
+    pub(crate) hash_chain: bool,
+    ///Permissions of the file on Unix
+    pub(crate) mode: Option<u32>,
+    pub(crate) failure_policy: WriteFailurePolicy,
+    pub(crate) on_error: Option<crate::SharedErrorHandler>,
+}
+
+struct OpenFile {
+    file: std::fs::File,
+    path: PathBuf,
+    //Set by the write failure policy
+    disabled: bool,
+    last_sync: Instant,
+    #[cfg(feature = "audit")]
+    chain: Option<crate::audit::HashChain>,
+}
+
+impl OpenFile {
+    ///Appends the hash chain to the lines of `text`, if it's enabled
+    fn link<'a>(&mut self, text: &'a str) -> Cow<'a, str> {
+        #[cfg(feature = "audit")]
+        if let Some(chain) = &mut self.chain {
+            return Cow::Owned(chain.link(text));
+        }
+        Cow::Borrowed(text)
+    }
+
+    ///Continues the hash chain from the last line of the file, after a different file was opened
+    fn resume_chain(&mut self) {
+        #[cfg(feature = "audit")]
+        if let Some(chain) = &mut self.chain {
+            *chain = crate::audit::HashChain::resume(&self.path);
+        }
+    }
+}
+
+///Writes records to the log file
+pub(crate) struct FileSink {
+    file: RwLock<OpenFile>,
+    options: FileOptions,
+    pub(crate) rotator: Option<Rotator>,
+    //Tracked separately to avoid querying the metadata on every write
+    size: AtomicU64,
+    //Set after a failed write until a write succeeds, so the failure is only reported once
+    failing: AtomicBool,
+}
+
+impl FileSink {
+    ///Opens the file at `path` for appending, creating it if it doesn't exist
+    pub(crate) fn open(
+        path: &Path,
+        options: FileOptions,
+        rotation: Option<FileRotationPolicy>,
+    ) -> std::io::Result<Self> {
+        let file = open_file(path, options.multi_process_safe, options.mode)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            file: RwLock::new(OpenFile {
+                file,
+                path: path.to_owned(),
+                disabled: false,
+                last_sync: Instant::now(),
+                #[cfg(feature = "audit")]
+                chain: options
+                    .hash_chain
+                    .then(|| crate::audit::HashChain::resume(path)),
+            }),
+            rotator: rotation.map(|r| Rotator::new(r, options.on_error.clone())),
+            options,
+            size: AtomicU64::new(size),
+            failing: AtomicBool::new(false),
+        })
+    }
+
+    ///Writes text that is not a record, e.g. the session banner
+    pub(crate) fn write_text(&self, text: &str) -> std::io::Result<()> {
+        let mut f = self.file.write();
+        let text = f.link(text);
+        write_to_file(
+            &mut f.file,
+            text.as_bytes(),
+            self.options.multi_process_safe,
+        )?;
+        self.size.fetch_add(text.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    ///Current size of the file
+    pub(crate) fn size(&self) -> Option<u64> {
+        self.file.read().file.metadata().map(|m| m.len()).ok()
+    }
+
+    ///Opens the file at the path again, for when it was moved by another program
+    ///
+    ///Writing is resumed if it was stopped by the write failure policy
+    pub(crate) fn reopen(&self) -> std::io::Result<()> {
+        let mut f = self.file.write();
+        let file = open_file(&f.path, self.options.multi_process_safe, self.options.mode)?;
+        let size = file.metadata()?.len();
+
+        f.file.flush()?;
+        f.file = file;
+        f.disabled = false;
+        f.resume_chain();
+        self.size.store(size, Ordering::Relaxed);
+        self.failing.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    ///Moves the current file out of the way and replaces it with a new one
+    fn rotate(&self, f: &mut OpenFile, rotator: &Rotator) -> std::io::Result<()> {
+        f.file.flush()?;
+        rotator.rotate(&f.path)?;
+        f.file = open_file(&f.path, self.options.multi_process_safe, self.options.mode)?;
+        self.size.store(0, Ordering::Relaxed);
+        f.resume_chain();
+        rotator.prune(&f.path)
+    }
+
+    ///Applies the write failure policy, returns the error to report
+    fn write_failed(
+        &self,
+        f: &mut OpenFile,
+        data: &[u8],
+        e: &std::io::Error,
+    ) -> Option<LoggerError> {
+        let first = !self.failing.swap(true, Ordering::Relaxed);
+        let error = |message: String| {
+            Some(LoggerError::WriteError(std::io::Error::new(
+                e.kind(),
+                message,
+            )))
+        };
+
+        match &self.options.failure_policy {
+            WriteFailurePolicy::Retry => {
+                first.then(|| LoggerError::WriteError(std::io::Error::new(e.kind(), e.to_string())))
+            }
+            WriteFailurePolicy::Disable => {
+                f.disabled = true;
+                error(format!("{e}, writing to it is stopped"))
+            }
+            //Already writing to the fallback file
+            WriteFailurePolicy::Fallback(path) if *path == f.path => {
+                f.disabled = true;
+                error(format!("{e}, writing to the fallback file is stopped"))
+            }
+            WriteFailurePolicy::Fallback(path) => {
+                let mp = self.options.multi_process_safe;
+                let fallback = open_file(path, mp, self.options.mode).and_then(|mut file| {
+                    write_to_file(&mut file, data, mp)?;
+                    Ok(file)
+                });
+
+                match fallback {
+                    Ok(file) => {
+                        self.size
+                            .store(file.metadata().map_or(0, |m| m.len()), Ordering::Relaxed);
+                        f.file = file;
+                        f.path.clone_from(path);
+                        f.resume_chain();
+                        self.failing.store(false, Ordering::Relaxed);
+                        error(format!("{e}, writing to {} instead", path.display()))
+                    }
+                    Err(fallback_error) => {
+                        f.disabled = true;
+                        error(format!(
+                            "{e} and to the fallback file {fallback_error}, writing to it is stopped"
+                        ))
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&self, record: &FormattedRecord) {
+        let mut errors = Vec::new();
+
+        {
+            let mut f = self.file.write();
+            if f.disabled {
+                return;
+            }
+
+            if let Some(rotator) = &self.rotator {
+                //Other processes append to the file as well
+                if self.options.multi_process_safe {
+                    if let Ok(metadata) = f.file.metadata() {
+                        self.size.store(metadata.len(), Ordering::Relaxed);
+                    }
+                }
+                let size = record.output.len();
+                if rotator.should_rotate(self.size.load(Ordering::Relaxed), size) {
+                    if let Err(e) = self.rotate(&mut f, rotator) {
+                        errors.push(LoggerError::RotationError(e));
+                    }
+                }
+            }
+
+            let text = f.link(record.output);
+            let data = text.as_bytes();
+            match write_to_file(&mut f.file, data, self.options.multi_process_safe) {
+                Ok(()) => {
+                    self.failing.store(false, Ordering::Relaxed);
+                    self.size.fetch_add(data.len() as u64, Ordering::Relaxed);
+                    let sync = match self.options.sync_mode {
+                        SyncMode::EveryRecord => true,
+                        SyncMode::Interval(interval) => f.last_sync.elapsed() >= interval,
+                        SyncMode::Never => false,
+                    };
+                    if sync
+                        || self.options.force_flush_on_error && record.level() == log::Level::Error
+                    {
+                        f.last_sync = Instant::now();
+                        if let Err(e) = f.file.sync_data() {
+                            errors.push(LoggerError::WriteError(e));
+                        }
+                    }
+                }
+                Err(e) => errors.extend(self.write_failed(&mut f, data, &e)),
+            }
+        }
+
+        //Reported after releasing the lock, as the handler may write to the file again
+        for e in errors {
+            crate::report_error(self.options.on_error.as_ref(), e);
+        }
+    }
+
+    fn flush(&self) {
+        let result = self.file.write().file.flush();
+        if let Err(e) = result {
+            crate::report_error(self.options.on_error.as_ref(), LoggerError::WriteError(e));
+        }
+    }
+}
+
+///Opens the file for writing at its end
+fn open_file(path: &Path, append: bool, mode: Option<u32>) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options
+        .write(true)
+        .append(append)
+        .create(true)
+        .truncate(false);
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+    }
+
+    let mut file = options.open(path)?;
+    //The mode only applies to new files, existing files are changed as well
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+    }
+    file.seek(SeekFrom::End(0))?;
+    Ok(file)
+}
+
+///Keeps the last `max_bytes` of output in memory
+pub(crate) struct MemorySink {
+    pub(crate) buffer: Arc<Mutex<Vec<u8>>>,
+    pub(crate) max_bytes: usize,
+}
+
+impl Sink for MemorySink {
+    fn write(&self, record: &FormattedRecord) {
+        write_to_memory(&self.buffer, self.max_bytes, record.output.as_bytes());
+    }
+}
+
+///Keeps the last `capacity` records
+pub(crate) struct RecentSink {
+    pub(crate) records: Arc<Mutex<VecDeque<String>>>,
+    pub(crate) capacity: usize,
+}
+
+impl Sink for RecentSink {
+    fn write(&self, record: &FormattedRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record.output.to_owned());
+    }
+}
+
+///Collects all records into a vector
+pub(crate) struct CaptureSink(pub(crate) Arc<Mutex<Vec<String>>>);
+
+impl Sink for CaptureSink {
+    fn write(&self, record: &FormattedRecord) {
+        self.0.lock().unwrap().push(record.output.to_owned());
+    }
+}
+
+fn write_to_file(file: &mut std::fs::File, data: &[u8], lock: bool) -> std::io::Result<()> {
+    //Only hold the lock for the duration of the write
+    if lock {
+        file.lock()?;
+    }
+    let result = write_record(file, data);
+    if lock {
+        //A failed write is the more useful error
+        let unlocked = file.unlock();
+        result?;
+        return unlocked;
+    }
+    result
+}
+
+///Writes the whole record, retrying on partial writes and interrupts
+pub(crate) fn write_record(writer: &mut impl Write, data: &[u8]) -> std::io::Result<()> {
+    writer.write_all(data)
+}
+
+fn write_to_memory(buffer: &Mutex<Vec<u8>>, max_bytes: usize, data: &[u8]) {
+    let mut buffer = buffer.lock().unwrap();
+    buffer.extend_from_slice(data);
+
+    //Drop the oldest lines, so no line or character is cut in half
+    if buffer.len() > max_bytes {
+        let excess = buffer.len() - max_bytes;
+        let end = buffer[excess - 1..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(buffer.len(), |i| excess + i);
+        buffer.drain(..end);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use wasm_bindgen::JsValue;
+use web_sys::{js_sys, Blob, BlobPropertyBag, Url};
+
+use crate::{FormattedRecord, Sink};
+
+///Keeps the most recent records in the `localStorage` of the browser, so they survive page reloads
+///
+///The records are stored as text under `key`. Once they take up more than the size cap, the oldest
+///records are removed. The records of the previous session can be read with
+///[`StorageSink::load`], e.g. after the page was reloaded because of a crash.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, StorageSink};
+///
+///let previous = StorageSink::load("my_game_logs");
+///
+///Builder::new()
+///    .add_sink(Box::new(StorageSink::new("my_game_logs").max_bytes(256 * 1024)))
+///    .init()
+///    .unwrap();
+///```
+pub struct StorageSink {
+    key: String,
+    max_bytes: usize,
+    //Records stored under the key and their total size
+    lines: Mutex<(VecDeque<String>, usize)>,
+}
+
+impl StorageSink {
+    ///Creates a sink that stores the records under `key`, keeping the records that are already
+    ///stored
+    #[must_use]
+    pub fn new(key: &str) -> Self {
+        let lines: VecDeque<String> = Self::load(key)
+            .unwrap_or_default()
+            .split_inclusive('\n')
+            .map(str::to_owned)
+            .collect();
+        let size = lines.iter().map(String::len).sum();
+
+        Self {
+            key: key.to_owned(),
+            max_bytes: 1024 * 1024,
+            lines: Mutex::new((lines, size)),
+        }
+    }
+
+    ///Sets the maximum size of the stored records, 1 MiB by default
+    ///
+    ///Browsers usually limit the storage of a site to around 5 MiB
+    #[must_use]
+    pub const fn max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    ///Returns the records stored under `key`
+    ///
+    ///Returns `None` if there are none or if `localStorage` is not available
+    #[must_use]
+    pub fn load(key: &str) -> Option<String> {
+        storage()?.get_item(key).ok()?
+    }
+
+    ///Creates an object URL of a text file with the records stored under `key`, that can be used
+    ///as the `href` of a download link
+    ///
+    ///Returns `None` if there are no records or if the URL could not be created. It should be
+    ///released with `URL.revokeObjectURL` once the file is downloaded.
+    #[must_use]
+    pub fn blob_url(key: &str) -> Option<String> {
+        blob_url(&Self::load(key)?)
+    }
+
+    ///Removes the records stored under `key`
+    pub fn clear(key: &str) {
+        if let Some(storage) = storage() {
+            let _ = storage.remove_item(key);
+        }
+    }
+}
+
+///Creates an object URL of a plain text blob
+pub(crate) fn blob_url(text: &str) -> Option<String> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(text));
+    let mut options = BlobPropertyBag::new();
+    options.type_("text/plain");
+
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options).ok()?;
+    Url::create_object_url_with_blob(&blob).ok()
+}
+
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+impl Sink for StorageSink {
+    fn write(&self, record: &FormattedRecord) {
+        let mut lines = self.lines.lock().unwrap();
+        let (lines, size) = &mut *lines;
+
+        lines.push_back(record.output.to_owned());
+        *size += record.output.len();
+        while *size > self.max_bytes && lines.len() > 1 {
+            *size -= lines.pop_front().map_or(0, |l| l.len());
+        }
+
+        let Some(storage) = storage() else {
+            return;
+        };
+        //If the storage is full, the older half of the records is removed and storing is retried
+        for _ in 0..2 {
+            let text: String = lines.iter().map(String::as_str).collect();
+            if storage.set_item(&self.key, &text).is_ok() {
+                return;
+            }
+            for _ in 0..lines.len() / 2 {
+                *size -= lines.pop_front().map_or(0, |l| l.len());
+            }
+        }
+    }
+}
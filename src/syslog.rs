@@ -0,0 +1,193 @@
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    path::Path,
+    sync::Mutex,
+};
+
+use crate::{resolve, severity, sink::SinkErrors, tcp::TcpConnection, FormattedRecord, Sink};
+
+///Syslog facility, the kind of program that logs the messages
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+enum Transport {
+    Udp(UdpSocket),
+    Tcp(TcpConnection),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixDatagram),
+}
+
+///Sends records to a syslog server, in the RFC 5424 format
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, Facility, SyslogSink};
+///
+///let syslog = SyslogSink::udp("127.0.0.1:514")
+///    .unwrap()
+///    .facility(Facility::Daemon)
+///    .app_name("my_app");
+///
+///Builder::new().add_sink(Box::new(syslog)).init().unwrap();
+///```
+pub struct SyslogSink {
+    transport: Mutex<Transport>,
+    facility: Facility,
+    app_name: String,
+    hostname: String,
+    errors: SinkErrors,
+}
+
+impl SyslogSink {
+    fn new(transport: Transport) -> Self {
+        let app_name = std::env::current_exe()
+            .ok()
+            .and_then(|p| Some(p.file_stem()?.to_string_lossy().into_owned()))
+            .unwrap_or_default();
+
+        Self {
+            transport: Mutex::new(transport),
+            facility: Facility::default(),
+            app_name: header_field(&app_name, 48),
+            hostname: header_field(crate::hostname().unwrap_or_default(), 255),
+            errors: SinkErrors::default(),
+        }
+    }
+
+    ///Sends records over UDP to the server at `addr`, usually on port 514
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the address can't be resolved or the socket can't be created
+    pub fn udp(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let addr = resolve(addr)?[0];
+        let local: SocketAddr = if addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0; 16], 0).into()
+        };
+
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        Ok(Self::new(Transport::Udp(socket)))
+    }
+
+    ///Sends records over TCP to the server at `addr`, usually on port 601
+    ///
+    ///Messages are framed with their length, as described in RFC 6587. If the connection is lost it
+    ///is reestablished on the next record. If that fails, reconnecting is retried after a delay that
+    ///grows up to 30 seconds, the messages in between are dropped.
+    ///
+    /// # Errors
+    ///
+    /// returns an error if the address can't be resolved or the connection fails
+    pub fn tcp(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let connection = TcpConnection::connect(resolve(addr)?)?;
+        Ok(Self::new(Transport::Tcp(connection)))
+    }
+
+    ///Sends records to the local syslog daemon over the Unix socket at `path`, usually `/dev/log`
+    ///
+    /// # Errors
+    ///
+    /// returns an error if failed to connect to the socket
+    #[cfg(unix)]
+    pub fn unix(path: &Path) -> std::io::Result<Self> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self::new(Transport::Unix(socket)))
+    }
+
+    ///Sets the facility of the messages, `User` by default
+    #[must_use]
+    pub const fn facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    ///Sets the name of the application in the messages, the name of the executable by default
+    ///
+    ///Spaces and characters other than printable ASCII are removed and the name is cut off after
+    ///48 characters, as required by RFC 5424
+    #[must_use]
+    pub fn app_name(mut self, name: &str) -> Self {
+        self.app_name = header_field(name, 48);
+        self
+    }
+
+    ///Formats the record as an RFC 5424 message
+    pub(crate) fn message(&self, record: &FormattedRecord) -> String {
+        let pri = self.facility as u8 * 8 + severity(record.level());
+        let time = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+        let pid = std::process::id();
+
+        format!(
+            "<{pri}>1 {time} {} {} {pid} - - {}: {}{}",
+            self.hostname,
+            self.app_name,
+            record.target,
+            crate::format_context(record.context),
+            record.message
+        )
+    }
+}
+
+///Limits a header field to printable ASCII without spaces and `max` characters, `-` if nothing is
+///left
+fn header_field(value: &str, max: usize) -> String {
+    let field: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max)
+        .collect();
+    if field.is_empty() {
+        "-".to_owned()
+    } else {
+        field
+    }
+}
+
+impl Sink for SyslogSink {
+    fn write(&self, record: &FormattedRecord) {
+        let message = self.message(record);
+        let result = match &mut *self.transport.lock().unwrap() {
+            Transport::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Tcp(connection) => {
+                let framed = format!("{} {message}", message.len());
+                connection.write_all(framed.as_bytes())
+            }
+            #[cfg(unix)]
+            Transport::Unix(socket) => socket.send(message.as_bytes()).map(|_| ()),
+        };
+
+        if let Err(e) = result {
+            self.errors.report("syslog", &e);
+        }
+    }
+
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+        self.errors.set(handler);
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    io::Write,
+    net::{SocketAddr, TcpStream},
+    time::{Duration, Instant},
+};
+
+///How long connecting to an address may take, so a server that is down doesn't stall the logger
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+///Wait before the first reconnect attempt after one failed, doubled after every failed attempt
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+///TCP connection that is reestablished when it's lost, backing off while the server is down
+pub(crate) struct TcpConnection {
+    addrs: Vec<SocketAddr>,
+    stream: Option<TcpStream>,
+    //No reconnect is attempted before this time
+    retry_at: Instant,
+    backoff: Duration,
+}
+
+impl TcpConnection {
+    ///Connects to the first of the `addrs` that accepts the connection
+    pub(crate) fn connect(addrs: Vec<SocketAddr>) -> std::io::Result<Self> {
+        let stream = connect(&addrs)?;
+        Ok(Self {
+            addrs,
+            stream: Some(stream),
+            retry_at: Instant::now(),
+            backoff: MIN_BACKOFF,
+        })
+    }
+
+    ///Writes the data, reconnecting first if the connection was lost
+    ///
+    ///The data is dropped while waiting to reconnect after a failed attempt
+    pub(crate) fn write_all(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None if Instant::now() < self.retry_at => return Ok(()),
+            None => match connect(&self.addrs) {
+                Ok(stream) => {
+                    self.backoff = MIN_BACKOFF;
+                    self.stream.insert(stream)
+                }
+                Err(e) => {
+                    self.retry_at = Instant::now() + self.backoff;
+                    self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+                    return Err(e);
+                }
+            },
+        };
+
+        //The next record tries to reconnect right away
+        let result = stream.write_all(data);
+        if result.is_err() {
+            self.stream = None;
+        }
+        result
+    }
+}
+
+fn connect(addrs: &[SocketAddr]) -> std::io::Result<TcpStream> {
+    let mut error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                //A server that stops reading must not stall the logger either
+                stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+                return Ok(stream);
+            }
+            Err(e) => error = Some(e),
+        }
+    }
+    Err(error.unwrap_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "No address to connect to")
+    }))
+}
@@ -0,0 +1,155 @@
+//!Capturing of records in tests
+//!
+//!Example:
+//!```
+//!use lunar_logger::test;
+//!
+//!let capture = test::capture();
+//!log::warn!("Request timed out");
+//!
+//!capture.assert_logged(log::Level::Warn, "timed out");
+//!capture.assert_not_logged(log::Level::Error, "timed out");
+//!```
+use std::{
+    cell::RefCell,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+type Store = Arc<Mutex<Vec<CapturedRecord>>>;
+
+thread_local! {
+    static CAPTURES: RefCell<Vec<Store>> = const { RefCell::new(Vec::new()) };
+}
+
+///A record logged while capturing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedRecord {
+    pub level: log::Level,
+    pub target: String,
+    pub message: String,
+}
+
+///Records logged from the current thread, since [`capture`] was called
+///
+///Capturing stops when it's dropped
+#[must_use = "capturing stops when the capture is dropped"]
+pub struct Capture {
+    store: Store,
+    //Only records of the thread that started capturing are stored
+    _not_send: PhantomData<*const ()>,
+}
+
+///Starts capturing all records logged from the current thread
+///
+///Works independently of the program logger, which still receives the records if it's enabled.
+///Since tests run in parallel, records of other threads are not captured. Records above the max
+///level of the [`log`] crate are not captured, see [`log::set_max_level`]. The redactions of the
+///program logger are applied to the captured records.
+///
+///Example:
+///```
+///use lunar_logger::{test, Builder};
+///
+///Builder::new().redact_keyword("hunter2").init().unwrap();
+///let capture = test::capture();
+///log::info!("Logged in with hunter2");
+///
+///capture.assert_logged(log::Level::Info, "Logged in with [REDACTED]");
+///capture.assert_not_logged(log::Level::Info, "hunter2");
+///```
+///
+/// # Panics
+///
+/// panics if a logger other than `lunar-logger` is set with [`log::set_logger`]
+pub fn capture() -> Capture {
+    crate::install_proxy().expect("Another logger is already set");
+    if crate::global_logger().is_none() {
+        log::set_max_level(log::LevelFilter::Trace);
+    }
+
+    let store = Store::default();
+    CAPTURES.with(|c| c.borrow_mut().push(store.clone()));
+
+    Capture {
+        store,
+        _not_send: PhantomData,
+    }
+}
+
+///Checks whether records of the current thread are captured
+pub(crate) fn capturing() -> bool {
+    CAPTURES
+        .try_with(|c| !c.borrow().is_empty())
+        .unwrap_or_default()
+}
+
+///Stores the record in all captures of the current thread
+pub(crate) fn capture_record(record: &log::Record) {
+    let _ = CAPTURES.try_with(|captures| {
+        for store in captures.borrow().iter() {
+            store.lock().unwrap().push(CapturedRecord {
+                level: record.level(),
+                target: record.target().to_owned(),
+                message: record.args().to_string(),
+            });
+        }
+    });
+}
+
+impl Capture {
+    ///Returns the captured records, oldest first
+    #[must_use]
+    pub fn records(&self) -> Vec<CapturedRecord> {
+        self.store.lock().unwrap().clone()
+    }
+
+    ///Removes all captured records
+    pub fn clear(&self) {
+        self.store.lock().unwrap().clear();
+    }
+
+    ///Checks whether a record of the `level` whose message contains `text` was captured
+    #[must_use]
+    pub fn logged(&self, level: log::Level, text: &str) -> bool {
+        self.store
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|r| r.level == level && r.message.contains(text))
+    }
+
+    ///Asserts that a record of the `level` whose message contains `text` was captured
+    ///
+    /// # Panics
+    ///
+    /// panics with the captured records if there is no such record
+    #[track_caller]
+    pub fn assert_logged(&self, level: log::Level, text: &str) {
+        assert!(
+            self.logged(level, text),
+            "No {level} record containing {text:?} was logged, captured records: {:#?}",
+            self.records()
+        );
+    }
+
+    ///Asserts that no record of the `level` whose message contains `text` was captured
+    ///
+    /// # Panics
+    ///
+    /// panics if there is such a record
+    #[track_caller]
+    pub fn assert_not_logged(&self, level: log::Level, text: &str) {
+        assert!(
+            !self.logged(level, text),
+            "A {level} record containing {text:?} was logged, captured records: {:#?}",
+            self.records()
+        );
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        let _ = CAPTURES.try_with(|c| c.borrow_mut().retain(|s| !Arc::ptr_eq(s, &self.store)));
+    }
+}
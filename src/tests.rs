@@ -12,24 +12,2428 @@ fn test_filter() {
 
     assert!(!filter("something", FilterType::Crate, target));
     assert!(!filter("tests", FilterType::Module, target));
+
+    assert!(filter("tests::something", FilterType::Path, target));
+    assert!(filter("tests", FilterType::Path, "tests"));
+    assert!(!filter("tests::some", FilterType::Path, target));
 }
 
 #[test]
 fn test_builder() {
-    crate::Builder::new()
+    let handle = crate::Builder::new()
         .add_mod_filter("stuff", log::LevelFilter::Info)
         .add_crate_filter("wgpu", LevelFilter::Warn)
         .log_to_file()
-        .log_filname(&(PathBuf::from_str("./test.log").unwrap()))
+        .log_filname(&temp_path("test.log"))
         .time_format("%Y")
         .default_filter(LevelFilter::Trace)
         .create()
         .enable_logger()
         .unwrap();
+    handle.set_color(false);
 
     log::trace!("TEST");
     log::debug!("TEST");
     log::info!("TEST");
     log::warn!("TEST");
     log::error!("TEST");
+
+    //A second logger is rejected before it touches its log file
+    let path = temp_path("second.log");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "previous run\n").unwrap();
+    let result = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&path)
+        .init();
+    assert!(matches!(result, Err(LoggerError::LoggerAlreadySet)));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous run\n");
+}
+
+fn log_record(logger: &Logger, level: log::Level, target: &str, msg: &str) {
+    log::Log::log(
+        logger,
+        &log::Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{msg}"))
+            .build(),
+    );
+}
+
+fn memory_contents(logger: &Logger) -> String {
+    let buffer = logger.memory_buffer.as_ref().unwrap();
+    String::from_utf8(buffer.lock().unwrap().clone()).unwrap()
+}
+
+#[test]
+fn test_memory_sink() {
+    let mut logger = Logger::new();
+    logger.use_color(false);
+    let buffer = logger.log_to_memory(64);
+
+    log_record(&logger, log::Level::Info, "tests", "first");
+    assert!(String::from_utf8_lossy(&buffer.lock().unwrap()).ends_with("first\n"));
+
+    for _ in 0..10 {
+        log_record(&logger, log::Level::Info, "tests", "sécond");
+    }
+    let contents = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+    assert!(contents.len() <= 64);
+    //Only whole lines are kept
+    assert!(contents.starts_with('['));
+    assert!(contents.ends_with("sécond\n"));
+    assert!(!contents.contains("first"));
+}
+
+#[test]
+fn test_filter_file() {
+    let filters = filter_file::parse("#Comment\n\ncrate wgpu warn\n  Module net TRACE\n").unwrap();
+
+    assert_eq!(filters.len(), 2);
+    assert_eq!(filters[0].0, "wgpu");
+    assert!(matches!(filters[0].1, FilterType::Crate));
+    assert_eq!(filters[0].2, LevelFilter::Warn);
+    assert_eq!(filters[1].0, "net");
+    assert!(matches!(filters[1].1, FilterType::Module));
+    assert_eq!(filters[1].2, LevelFilter::Trace);
+
+    assert!(filter_file::parse("crate wgpu").is_err());
+    assert!(filter_file::parse("something wgpu warn").is_err());
+    assert!(filter_file::parse("crate wgpu loud").is_err());
+}
+
+#[test]
+fn test_filter_file_error() {
+    let errors = Arc::new(Mutex::new(0));
+    let handler_errors = Arc::clone(&errors);
+
+    //Reported instead of panicking
+    let _logger = crate::Builder::new()
+        .on_error(Box::new(move |_| *handler_errors.lock().unwrap() += 1))
+        .filter_file(&temp_path("missing_filters.txt"))
+        .create();
+
+    assert_eq!(*errors.lock().unwrap(), 1);
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn test_watch_filter_file() {
+    let path = temp_path("watched_filters.txt");
+    std::fs::write(&path, "crate wgpu warn\n").unwrap();
+
+    let logger = crate::Builder::new()
+        .add_crate_filter("net", LevelFilter::Debug)
+        .watch_filter_file(&path)
+        .create();
+
+    //The filters of the file are added to the ones set in code
+    assert_eq!(logger.target_level("wgpu").0, LevelFilter::Warn);
+    assert_eq!(logger.target_level("net").0, LevelFilter::Debug);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_regex_filter() {
+    let mut logger = Logger::new();
+    logger.use_color(false);
+    logger.add_filter("wgpu", FilterType::Crate, LevelFilter::Error);
+    logger
+        .add_regex_filter(r"^wgpu(_\w+)?::", LevelFilter::Trace)
+        .unwrap();
+    logger
+        .add_regex_filter("(", LevelFilter::Trace)
+        .unwrap_err();
+    let buffer = logger.log_to_memory(1024);
+
+    //Exact filters take priority over regex filters
+    log_record(&logger, log::Level::Warn, "wgpu::device", "exact");
+    log_record(&logger, log::Level::Trace, "wgpu_hal::vulkan", "regex");
+    log_record(&logger, log::Level::Trace, "other", "default");
+
+    let contents = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+    assert!(!contents.contains("exact"));
+    assert!(contents.contains("regex"));
+    assert!(!contents.contains("default"));
+    assert_eq!(logger.max_level(), LevelFilter::Trace);
+}
+
+#[test]
+fn test_filter_table() {
+    let mut logger = Logger::new();
+    logger.add_filter("wgpu", FilterType::Crate, LevelFilter::Warn);
+    logger.add_filter("net", FilterType::Module, LevelFilter::Trace);
+
+    let table = logger.filter_table();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], "┌──────┬─────────┬───────┐");
+    assert_eq!(lines[1], "│ Name │ Type    │ Level │");
+    assert_eq!(lines[3], "│ wgpu │ Crate   │ Warn  │");
+    assert_eq!(lines[5], "│ *    │ Default │ Info  │");
+    assert_eq!(lines[6], "└──────┴─────────┴───────┘");
+}
+
+#[test]
+fn test_strip_prefix() {
+    let target = "my_crate::internal::net::client";
+    assert_eq!(strip_prefix(target, "my_crate::internal"), "net::client");
+    assert_eq!(strip_prefix(target, "my_crate::inter"), target);
+    assert_eq!(strip_prefix(target, "other"), target);
+    assert_eq!(strip_prefix("my_crate", "my_crate"), "my_crate");
+
+    let mut logger = Logger::new();
+    logger.use_color(false);
+    logger.add_path_prefix_strip("my_crate");
+    logger.add_path_prefix_strip("internal");
+    let buffer = logger.log_to_memory(1024);
+
+    log_record(&logger, log::Level::Info, target, "msg");
+    let contents = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+    assert!(contents.contains(" net::client] msg"));
+}
+
+#[test]
+fn test_builder_as_logger() {
+    let builder = crate::Builder::new()
+        .use_color(false)
+        .add_crate_filter("noisy", LevelFilter::Off)
+        .memory_sink(1024);
+
+    log::Log::log(
+        &builder,
+        &log::Record::builder()
+            .level(log::Level::Info)
+            .target("tests")
+            .args(format_args!("proxied"))
+            .build(),
+    );
+    log::Log::log(
+        &builder,
+        &log::Record::builder()
+            .level(log::Level::Error)
+            .target("noisy")
+            .args(format_args!("filtered"))
+            .build(),
+    );
+
+    let logger = builder.logger();
+    let contents = memory_contents(logger);
+    assert!(contents.contains("proxied"));
+    assert!(!contents.contains("filtered"));
+    assert_eq!(builder.max_level(), LevelFilter::Info);
+}
+
+#[test]
+fn test_rotation_naming() {
+    let active = Path::new("logs/app.log");
+
+    let policy = FileRotationPolicy::new();
+    assert_eq!(policy.rotated_path(active, 1), Path::new("logs/app.log.1"));
+
+    let policy = FileRotationPolicy::new().naming_template("app-{date}-{pid}-{index}.log");
+    assert_eq!(
+        policy.rotated_path(active, 2),
+        PathBuf::from(format!(
+            "logs/app-{}-{}-2.log",
+            get_time("%Y-%m-%d"),
+            std::process::id()
+        ))
+    );
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir()
+        .join("lunar-logger-tests")
+        .join(format!("{}-{name}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_multi_process_safe() {
+    let path = temp_path("multi_process.log");
+
+    let mut loggers = Vec::new();
+    for _ in 0..2 {
+        let mut logger = Logger::new();
+        logger.use_color(false);
+        logger.set_log_to_file();
+        logger.set_log_file_name(&path).unwrap();
+        logger.set_multi_process_safe(true);
+        logger.open_log_file().unwrap();
+        loggers.push(logger);
+    }
+
+    //Separate file handles must not overwrite each other
+    log_record(&loggers[0], log::Level::Info, "tests", "first");
+    log_record(&loggers[1], log::Level::Info, "tests", "second");
+    log_record(&loggers[0], log::Level::Info, "tests", "third");
+
+    //A process started later must not truncate the file
+    let mut logger = Logger::new();
+    logger.set_log_to_file();
+    logger.set_log_file_name(&path).unwrap();
+    logger.set_multi_process_safe(true);
+    logger.open_log_file().unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].ends_with("first"));
+    assert!(lines[1].ends_with("second"));
+    assert!(lines[2].ends_with("third"));
+}
+
+#[test]
+fn test_output_hooks() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    let mut logger = Logger::new();
+    logger.use_color(false);
+    for i in 0..2 {
+        let calls = calls.clone();
+        logger.register_output_hook(Box::new(move |output, record| {
+            calls
+                .lock()
+                .unwrap()
+                .push(format!("{i} {} {output}", record.level()));
+        }));
+    }
+
+    log_record(&logger, log::Level::Warn, "tests", "hooked");
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 2);
+    assert!(calls[0].starts_with("0 WARN ["));
+    assert!(calls[1].starts_with("1 WARN ["));
+    assert!(calls[1].ends_with("tests] hooked\n"));
+}
+
+#[test]
+fn test_file_size() {
+    let mut logger = Logger::new();
+    assert_eq!(logger.current_file_size(), None);
+
+    logger.use_color(false);
+    logger.set_log_to_file();
+    logger
+        .set_log_file_name(&temp_path("file_size.log"))
+        .unwrap();
+    logger.open_log_file().unwrap();
+    assert_eq!(logger.current_file_size(), Some(0));
+
+    log_record(&logger, log::Level::Info, "tests", "size");
+    assert!(logger.current_file_size().unwrap() > 0);
+}
+
+#[test]
+fn test_context() {
+    let builder = crate::inject_cargo_metadata!(crate::Builder::new())
+        .add_context("id", "42")
+        .use_color(false)
+        .memory_sink(1024);
+    let logger = builder.create();
+
+    log_record(&logger, log::Level::Info, "tests", "msg");
+
+    let contents = memory_contents(&logger);
+    assert!(contents.ends_with(&format!(
+        "tests] [app=lunar-logger version={} id=42] msg\n",
+        env!("CARGO_PKG_VERSION")
+    )));
+}
+
+#[test]
+fn test_filter_trace() {
+    let mut logger = Logger::new();
+    logger.add_filter("wgpu", FilterType::Crate, LevelFilter::Warn);
+
+    let (level, source) = logger.target_level("wgpu::hal");
+    assert_eq!(
+        logger.filter_trace("wgpu::hal", LevelFilter::Debug, level, source),
+        "FILTER: target=wgpu::hal level=Debug matched crate-filter[wgpu]=Warn → suppressed"
+    );
+
+    let (level, source) = logger.target_level("tests");
+    assert_eq!(
+        logger.filter_trace("tests", LevelFilter::Info, level, source),
+        "FILTER: target=tests level=Info matched default=Info → logged"
+    );
+}
+
+#[test]
+fn test_partial_writes() {
+    ///Writes at most 3 bytes at a time and gets interrupted every other call
+    struct PartialWriter {
+        data: Vec<u8>,
+        interrupt: bool,
+    }
+
+    impl std::io::Write for PartialWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+
+            let len = buf.len().min(3);
+            self.data.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut writer = PartialWriter {
+        data: Vec::new(),
+        interrupt: false,
+    };
+    sink::write_record(&mut writer, b"[2024 INFO  tests] message\n").unwrap();
+    assert_eq!(writer.data, b"[2024 INFO  tests] message\n");
+}
+
+#[test]
+fn test_multiline_indent() {
+    assert_eq!(indent_lines("a\nb\nc", "  "), "a\n  b\n  c");
+    assert_eq!(indent_lines("a", "  "), "a");
+
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .multiline_indent("> ")
+        .memory_sink(1024)
+        .create();
+    log_record(&logger, log::Level::Info, "tests", "first\nsecond");
+
+    let contents = memory_contents(&logger);
+    assert!(contents.ends_with("tests] first\n> second\n"));
+}
+
+#[test]
+fn test_context_from_env() {
+    std::env::set_var("LUNAR_LOGGER_TEST_DEPLOYMENT", "prod-42");
+    std::env::remove_var("LUNAR_LOGGER_TEST_UNSET");
+
+    let logger = crate::Builder::new()
+        .context_from_env("LUNAR_LOGGER_TEST_DEPLOYMENT", "deployment")
+        .context_from_env("LUNAR_LOGGER_TEST_UNSET", "unset")
+        .create();
+
+    assert_eq!(
+        logger.context,
+        vec![("deployment".to_owned(), "prod-42".to_owned())]
+    );
+}
+
+#[test]
+fn test_silence() {
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .silence_crate("wgpu")
+        .silence_module("net")
+        .memory_sink(1024)
+        .create();
+
+    log_record(&logger, log::Level::Error, "wgpu::device", "crate");
+    log_record(&logger, log::Level::Error, "app::net::client", "module");
+    log_record(&logger, log::Level::Error, "app", "logged");
+
+    let contents = memory_contents(&logger);
+    assert!(!contents.contains("crate"));
+    assert!(!contents.contains("module"));
+    assert!(contents.contains("logged"));
+}
+
+#[test]
+fn test_timestamp_offset() {
+    let offset = chrono::FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+    assert_eq!(get_time_with_offset("%:z", &offset), "+05:30");
+
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .time_format("%:z")
+        .timestamp_offset(offset)
+        .memory_sink(1024)
+        .create();
+    log_record(&logger, log::Level::Info, "tests", "msg");
+
+    assert!(memory_contents(&logger).starts_with("[+05:30 INFO "));
+}
+
+#[test]
+fn test_parse_bare_level() {
+    assert_eq!(
+        crate::builder::parse_bare_level("debug"),
+        Some(LevelFilter::Debug)
+    );
+    assert_eq!(
+        crate::builder::parse_bare_level(" WARN "),
+        Some(LevelFilter::Warn)
+    );
+    assert_eq!(crate::builder::parse_bare_level("wgpu=warn"), None);
+    assert_eq!(crate::builder::parse_bare_level("loud"), None);
+}
+
+#[test]
+fn test_io_write() {
+    let mut logger = crate::Builder::new()
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    writeln!(logger, "written {}", 42).unwrap();
+    logger.flush().unwrap();
+
+    assert!(memory_contents(&logger).ends_with("INFO  io_write] written 42\n"));
+}
+
+#[test]
+fn test_sink_error_handler() {
+    struct Failing(crate::sink::SinkErrors);
+
+    impl Sink for Failing {
+        fn write(&self, _: &FormattedRecord) {
+            self.0
+                .report("failing", &std::io::Error::other("disk full"));
+        }
+
+        fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+            self.0.set(handler);
+        }
+    }
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let handler_errors = Arc::clone(&errors);
+
+    //The handler reaches sinks that were added before it was set
+    let mut logger = Logger::new();
+    logger.add_sink(Box::new(Failing(crate::sink::SinkErrors::default())));
+    logger.set_on_error(Box::new(move |e| {
+        handler_errors.lock().unwrap().push(e.to_string());
+    }));
+    log_record(&logger, log::Level::Info, "tests", "msg");
+
+    assert_eq!(
+        *errors.lock().unwrap(),
+        ["Failed to write to a sink failing: disk full"]
+    );
+}
+
+#[test]
+fn test_custom_sink() {
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Sink for Collect {
+        fn write(&self, record: &FormattedRecord) {
+            self.0.lock().unwrap().push(format!(
+                "{} {} {}",
+                record.level(),
+                record.target,
+                record.message
+            ));
+        }
+    }
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let logger = crate::Builder::new()
+        .add_path_prefix_strip("app")
+        .add_sink(Box::new(Collect(records.clone())))
+        .create();
+    log_record(&logger, log::Level::Warn, "app::net", "sunk");
+
+    assert_eq!(*records.lock().unwrap(), vec!["WARN net sunk".to_owned()]);
+}
+
+#[test]
+fn test_size_rotation() {
+    let path = temp_path("rotation/app.log");
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .log_to_file()
+        .log_filname(&path)
+        .rotate_size(100)
+        .create();
+    logger.open_log_file().unwrap();
+
+    //Each record is about 45 bytes, so only 2 fit in a file
+    for i in 0..5 {
+        log_record(&logger, log::Level::Info, "tests", &format!("record {i}"));
+    }
+
+    let read = |p: &Path| std::fs::read_to_string(p).unwrap();
+    let mut rotated_1 = path.as_os_str().to_owned();
+    rotated_1.push(".1");
+    let mut rotated_2 = path.as_os_str().to_owned();
+    rotated_2.push(".2");
+
+    assert!(read(Path::new(&rotated_1)).contains("record 0"));
+    assert!(read(Path::new(&rotated_1)).contains("record 1"));
+    assert!(read(Path::new(&rotated_2)).contains("record 3"));
+    assert!(read(&path).contains("record 4"));
+    assert!(read(&path).len() <= 100);
+}
+
+#[test]
+fn test_scheduled_rotation() {
+    let path = temp_path("scheduled/app.log");
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .log_to_file()
+        .log_filname(&path)
+        .rotate(Rotation::Daily)
+        .create();
+    logger.open_log_file().unwrap();
+    log_record(&logger, log::Level::Info, "tests", "yesterday");
+
+    //Pretend the file was opened yesterday
+    let log_file = logger.log_file.read().clone().unwrap();
+    let rotator = log_file.rotator.as_ref().unwrap();
+    *rotator.period.lock().unwrap() = "1970-01-01".to_owned();
+    log_record(&logger, log::Level::Info, "tests", "today");
+
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1970-01-01");
+    let rotated = std::fs::read_to_string(PathBuf::from(rotated)).unwrap();
+    assert!(rotated.contains("yesterday"));
+    assert!(!rotated.contains("today"));
+    assert!(std::fs::read_to_string(&path).unwrap().contains("today"));
+}
+
+#[test]
+fn test_rotation_retention() {
+    let path = temp_path("retention/app.log");
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .log_to_file()
+        .log_filname(&path)
+        .rotate_size(50)
+        .max_rotated_files(2)
+        .create();
+    logger.open_log_file().unwrap();
+
+    //One record per file
+    for i in 0..5 {
+        log_record(&logger, log::Level::Info, "tests", &format!("record {i}"));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let mut names: Vec<String> = std::fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+
+    let active = path.file_name().unwrap().to_str().unwrap();
+    assert_eq!(
+        names,
+        vec![
+            active.to_owned(),
+            format!("{active}.3"),
+            format!("{active}.4")
+        ]
+    );
+}
+
+#[test]
+fn test_rotation_retention_template() {
+    let path = temp_path("retention_template/app.log");
+    let dir = path.parent().unwrap().to_owned();
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("errors.log"), "").unwrap();
+
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .log_to_file()
+        .log_filname(&path)
+        .rotation_policy(
+            FileRotationPolicy::new()
+                .max_size(50)
+                .max_rotated_files(1)
+                .naming_template("{date}-{index}.log"),
+        )
+        .create();
+    logger.open_log_file().unwrap();
+
+    for i in 0..4 {
+        log_record(&logger, log::Level::Info, "tests", &format!("record {i}"));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let mut names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+
+    //Files that don't fit the template are not rotated files
+    assert_eq!(
+        names,
+        vec![
+            format!("{}-3.log", get_time("%Y-%m-%d")),
+            "app.log".to_owned(),
+            "errors.log".to_owned()
+        ]
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_compress_rotated() {
+    use std::io::Read;
+
+    let path = temp_path("compress/app.log");
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .log_to_file()
+        .log_filname(&path)
+        .rotate_size(50)
+        .compress_rotated(true)
+        .create();
+    logger.open_log_file().unwrap();
+
+    log_record(&logger, log::Level::Info, "tests", "compressed");
+    log_record(&logger, log::Level::Info, "tests", "active");
+
+    let mut gz_path = path.as_os_str().to_owned();
+    gz_path.push(".1.gz");
+    let gz_path = PathBuf::from(gz_path);
+
+    //Compression happens in the background
+    for _ in 0..100 {
+        if gz_path.exists() && !path.with_extension("log.1").exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let mut contents = String::new();
+    flate2::read::GzDecoder::new(std::fs::File::open(&gz_path).unwrap())
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert!(contents.contains("compressed"));
+}
+
+#[test]
+fn test_format_panic() {
+    let formatted = Arc::new(Mutex::new(String::new()));
+
+    let previous = std::panic::take_hook();
+    let captured = formatted.clone();
+    std::panic::set_hook(Box::new(move |info| {
+        *captured.lock().unwrap() = format_panic(info);
+    }));
+    let _ = std::thread::Builder::new()
+        .name("worker".to_owned())
+        .spawn(|| panic!("boom {}", 42))
+        .unwrap()
+        .join();
+    std::panic::set_hook(previous);
+
+    let formatted = formatted.lock().unwrap();
+    assert!(formatted.starts_with("thread 'worker' panicked at src/tests.rs:"));
+    assert!(formatted.ends_with("\nboom 42"));
+}
+
+#[test]
+fn test_handle_file_output() {
+    let path = temp_path("handle/app.log");
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+    let mut logger = Logger::new();
+    logger.use_color(false);
+    logger.set_log_file_name(&path).unwrap();
+    let handle = LoggerHandle::new(Arc::new(logger));
+    let logger = handle.logger().clone();
+
+    log_record(&logger, log::Level::Info, "tests", "before");
+    assert!(!path.exists());
+
+    handle.set_file_output(true).unwrap();
+    log_record(&logger, log::Level::Info, "tests", "enabled");
+    handle.set_file_output(false).unwrap();
+    log_record(&logger, log::Level::Info, "tests", "disabled");
+    handle.set_file_output(true).unwrap();
+    log_record(&logger, log::Level::Info, "tests", "reenabled");
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("before"));
+    assert!(!contents.contains("disabled"));
+    assert!(contents.contains("enabled\n"));
+    assert!(contents.ends_with("reenabled\n"));
+}
+
+#[test]
+fn test_handle_default_level() {
+    let mut logger = Logger::new();
+    logger.use_color(false);
+    logger.log_to_memory(1024);
+    let handle = LoggerHandle::new(Arc::new(logger));
+    let logger = handle.logger().clone();
+
+    log_record(&logger, log::Level::Debug, "tests", "hidden");
+    handle.set_default_level(LevelFilter::Debug);
+    log_record(&logger, log::Level::Debug, "tests", "shown");
+
+    let contents = memory_contents(&logger);
+    assert!(!contents.contains("hidden"));
+    assert!(contents.contains("shown"));
+    assert_eq!(logger.max_level(), LevelFilter::Debug);
+}
+
+#[test]
+fn test_handle_filters() {
+    let mut logger = Logger::new();
+    logger.use_color(false);
+    logger.log_to_memory(1024);
+    logger.add_filter("noisy", FilterType::Crate, LevelFilter::Trace);
+    let handle = LoggerHandle::new(Arc::new(logger));
+    let logger = handle.logger().clone();
+    assert_eq!(logger.max_level(), LevelFilter::Trace);
+
+    handle.add_filter("noisy", FilterType::Crate, LevelFilter::Off);
+    log_record(&logger, log::Level::Error, "noisy::net", "silenced");
+    assert_eq!(logger.filters.read().len(), 1);
+    assert_eq!(logger.max_level(), LevelFilter::Info);
+
+    assert!(handle.remove_filter("noisy", FilterType::Crate));
+    assert!(!handle.remove_filter("noisy", FilterType::Crate));
+    log_record(&logger, log::Level::Error, "noisy::net", "restored");
+
+    let contents = memory_contents(&logger);
+    assert!(!contents.contains("silenced"));
+    assert!(contents.contains("restored"));
+
+    //A path filter added at runtime takes precedence over the crate filter
+    handle.add_filter("noisy", FilterType::Crate, LevelFilter::Warn);
+    handle.add_filter("noisy::net", FilterType::Path, LevelFilter::Debug);
+    handle.add_filter("noisy::net::tcp", FilterType::Path, LevelFilter::Trace);
+    assert_eq!(logger.target_level("noisy::net").0, LevelFilter::Debug);
+    assert_eq!(logger.target_level("noisy::net::tcp").0, LevelFilter::Trace);
+    assert_eq!(logger.target_level("noisy::fs").0, LevelFilter::Warn);
+}
+
+#[test]
+fn test_parse_filters() {
+    let logger = crate::Builder::new()
+        .parse_filters("wgpu=warn, my_app=info,my_app::net=trace,debug")
+        .unwrap()
+        .create();
+
+    assert_eq!(logger.target_level("wgpu::hal").0, LevelFilter::Warn);
+    assert_eq!(logger.target_level("my_app::db").0, LevelFilter::Info);
+    assert_eq!(
+        logger.target_level("my_app::net::tcp").0,
+        LevelFilter::Trace
+    );
+    assert_eq!(logger.target_level("other").0, LevelFilter::Debug);
+
+    assert!(crate::Builder::new().parse_filters("wgpu=loud").is_err());
+    assert!(crate::Builder::new().parse_filters("=warn").is_err());
+}
+
+#[test]
+fn test_from_env() {
+    std::env::set_var(
+        "LUNAR_LOGGER_TEST_FILTERS",
+        "wgpu=trace,my_app::net=off,debug",
+    );
+
+    let logger = crate::Builder::from_env("LUNAR_LOGGER_TEST_FILTERS")
+        .add_crate_filter("wgpu", LevelFilter::Warn)
+        .add_crate_filter("naga", LevelFilter::Error)
+        .default_filter(LevelFilter::Info)
+        .create();
+
+    assert_eq!(logger.target_level("wgpu").0, LevelFilter::Trace);
+    assert_eq!(logger.target_level("naga").0, LevelFilter::Error);
+    assert_eq!(logger.target_level("my_app::net").0, LevelFilter::Off);
+    assert_eq!(logger.target_level("my_app").0, LevelFilter::Debug);
+
+    let logger = crate::Builder::from_env("LUNAR_LOGGER_TEST_UNSET").create();
+    assert_eq!(logger.target_level("my_app").0, LevelFilter::Info);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_config_file() {
+    let path = temp_path("config.toml");
+    std::fs::write(
+        &path,
+        r#"
+default_level = "debug"
+color = false
+time_format = "%Y"
+
+[filters]
+wgpu = "warn"
+"my_app::net" = "trace"
+
+[rotation]
+max_size = 100
+"#,
+    )
+    .unwrap();
+
+    let logger = crate::Builder::from_config_file(&path).unwrap().create();
+    assert_eq!(logger.target_level("wgpu::hal").0, LevelFilter::Warn);
+    assert_eq!(logger.target_level("my_app::net").0, LevelFilter::Trace);
+    assert_eq!(logger.target_level("my_app").0, LevelFilter::Debug);
+    assert!(!logger.use_color.load(Ordering::Relaxed));
+    assert_eq!(logger.time_format, "%Y");
+    assert!(logger.rotation.is_some());
+
+    std::fs::write(&path, "level = \"debug\"").unwrap();
+    assert!(matches!(
+        crate::Builder::from_config_file(&path),
+        Err(LoggerError::InvalidConfig(_))
+    ));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_config_reload() {
+    let logger = crate::Builder::new()
+        .add_crate_filter("old", LevelFilter::Off)
+        .create();
+
+    let config = crate::config::parse(
+        r#"
+default_level = "trace"
+color = false
+
+[filters]
+wgpu = "warn"
+"#,
+    )
+    .unwrap();
+    crate::config::reload(&logger, &config);
+
+    assert_eq!(logger.target_level("old").0, LevelFilter::Trace);
+    assert_eq!(logger.target_level("wgpu").0, LevelFilter::Warn);
+    assert!(!logger.use_color.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_reopen_log_file() {
+    let path = temp_path("reopen/app.log");
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .log_to_file()
+        .log_filname(&path)
+        .create();
+    logger.open_log_file().unwrap();
+    log_record(&logger, log::Level::Info, "tests", "before");
+
+    //What logrotate does before sending the signal
+    let moved = path.with_file_name("app.log.1");
+    std::fs::rename(&path, &moved).unwrap();
+    logger.reopen_requested.store(true, Ordering::Relaxed);
+    log_record(&logger, log::Level::Info, "tests", "after");
+
+    assert!(std::fs::read_to_string(&moved).unwrap().contains("before"));
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("before"));
+    assert!(contents.contains("after"));
+}
+
+#[test]
+fn test_color_detection() {
+    use std::io::IsTerminal;
+
+    let mut logger = Logger::new();
+    let expected = crate::sink::color_from_env(|var| std::env::var(var).ok())
+        .unwrap_or_else(|| std::io::stdout().is_terminal());
+    assert_eq!(logger.colored(log::Level::Info), expected);
+
+    logger.use_color(true);
+    assert!(logger.colored(log::Level::Info));
+    logger.use_color(false);
+    assert!(!logger.colored(log::Level::Info));
+}
+
+#[test]
+fn test_color_from_env() {
+    let env = |vars: &'static [(&'static str, &'static str)]| {
+        move |var: &str| {
+            vars.iter()
+                .find(|(k, _)| *k == var)
+                .map(|(_, v)| (*v).to_owned())
+        }
+    };
+
+    assert_eq!(crate::sink::color_from_env(env(&[])), None);
+    assert_eq!(
+        crate::sink::color_from_env(env(&[("NO_COLOR", "1")])),
+        Some(false)
+    );
+    assert_eq!(crate::sink::color_from_env(env(&[("NO_COLOR", "")])), None);
+    assert_eq!(
+        crate::sink::color_from_env(env(&[("CLICOLOR", "0")])),
+        Some(false)
+    );
+    assert_eq!(crate::sink::color_from_env(env(&[("CLICOLOR", "1")])), None);
+    assert_eq!(
+        crate::sink::color_from_env(env(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")])),
+        Some(true)
+    );
+    assert_eq!(
+        crate::sink::color_from_env(env(&[("CLICOLOR_FORCE", "0")])),
+        None
+    );
+}
+
+#[test]
+fn test_theme() {
+    let logger = crate::Builder::new()
+        .use_color(true)
+        .theme(Theme {
+            info: Color::Blue,
+            brackets: Color::Default,
+            ..Theme::default()
+        })
+        .create();
+
+    let line = console_line(&logger, log::Level::Info, "themed");
+    assert!(line.starts_with("[\x1b[0m"));
+    assert!(line.contains("\x1b[34mINFO "));
+}
+
+///Formats a record the way it's written to the console
+fn console_line(logger: &Logger, level: log::Level, msg: &str) -> String {
+    logger.format_line(
+        &FormattedRecord {
+            record: &log::Record::builder()
+                .level(level)
+                .target("tests")
+                .args(format_args!("{msg}"))
+                .build(),
+            timestamp: "time",
+            target: "tests",
+            message: msg,
+            context: &[],
+            fields: &[],
+            key_values: &[],
+            output: "",
+        },
+        true,
+    )
+}
+
+#[test]
+fn test_color_downgrade() {
+    use crate::theme::{ColorDepth, Fg};
+
+    let orange = Color::Rgb(255, 135, 0);
+    assert_eq!(orange.downgrade(ColorDepth::TrueColor), orange);
+    assert_eq!(orange.downgrade(ColorDepth::Ansi256), Color::Ansi256(208));
+    assert_eq!(orange.downgrade(ColorDepth::Basic), Color::Yellow);
+    assert_eq!(
+        Color::Rgb(128, 128, 128).downgrade(ColorDepth::Basic),
+        Color::BrightBlack
+    );
+    assert_eq!(
+        Color::Ansi256(9).downgrade(ColorDepth::Basic),
+        Color::BrightRed
+    );
+    assert_eq!(Color::Ansi256(21).downgrade(ColorDepth::Basic), Color::Blue);
+
+    assert_eq!(
+        Fg(orange, ColorDepth::TrueColor).to_string(),
+        "\x1b[38;2;255;135;0m"
+    );
+    assert_eq!(
+        Fg(orange, ColorDepth::Ansi256).to_string(),
+        "\x1b[38;5;208m"
+    );
+    assert_eq!(
+        Fg(Color::BrightBlack, ColorDepth::Basic).to_string(),
+        "\x1b[90m"
+    );
+    assert_eq!(Fg(Color::Default, ColorDepth::Basic).to_string(), "");
+
+    let env = |term: &'static str| move |var: &str| (var == "TERM").then(|| term.to_owned());
+    assert_eq!(
+        ColorDepth::detect(env("xterm-256color")),
+        ColorDepth::Ansi256
+    );
+    assert_eq!(ColorDepth::detect(env("xterm")), ColorDepth::Basic);
+}
+
+#[test]
+fn test_level_glyphs() {
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .level_glyphs(true)
+        .create();
+
+    assert!(console_line(&logger, log::Level::Error, "failed").starts_with("✖ ["));
+    assert!(console_line(&logger, log::Level::Warn, "careful").starts_with("⚠ ["));
+}
+
+#[test]
+fn test_level_labels() {
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .memory_sink(1024)
+        .level_label(log::Level::Warn, "WARNING")
+        .level_label(log::Level::Info, "info ")
+        .create();
+
+    log_record(&logger, log::Level::Warn, "tests", "careful");
+    log_record(&logger, log::Level::Info, "tests", "fine");
+    log_record(&logger, log::Level::Error, "tests", "failed");
+
+    let contents = memory_contents(&logger);
+    assert!(contents.contains(" WARNING tests] careful"));
+    assert!(contents.contains(" info  tests] fine"));
+    assert!(contents.contains(" ERROR tests] failed"));
+}
+
+#[test]
+fn test_plain_file_output() {
+    let logger = crate::Builder::new()
+        .use_color(true)
+        .level_glyphs(true)
+        .memory_sink(1024)
+        .create();
+
+    log_record(&logger, log::Level::Error, "tests", "failed");
+
+    let contents = memory_contents(&logger);
+    assert!(contents.starts_with('['));
+    assert!(!contents.contains('\x1b'));
+    assert!(console_line(&logger, log::Level::Error, "failed").contains('\x1b'));
+}
+
+#[test]
+fn test_file_format() {
+    let path = temp_path("json.log");
+    let hooked = Arc::new(Mutex::new(String::new()));
+    let hook = hooked.clone();
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .log_to_file()
+        .log_filname(&path)
+        .file_format(Format::Json)
+        .time_format("%Y")
+        .add_context("id", "42")
+        .memory_sink(1024)
+        .add_output_hook(Box::new(move |output, _| {
+            hook.lock().unwrap().push_str(output);
+        }))
+        .create();
+    logger.open_log_file().unwrap();
+
+    log_record(&logger, log::Level::Warn, "tests", "a \"quoted\"\nline");
+
+    let year = get_time("%Y");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        format!(
+            "{{\"timestamp\":\"{year}\",\"level\":\"WARN\",\"target\":\"tests\",\"message\":\"a \\\"quoted\\\"\\nline\",\"id\":\"42\"}}\n"
+        )
+    );
+    //Output hooks get the output of the log file
+    assert_eq!(
+        *hooked.lock().unwrap(),
+        std::fs::read_to_string(&path).unwrap()
+    );
+    //Other sinks are not affected
+    assert!(memory_contents(&logger).starts_with('['));
+
+    let custom = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&path)
+        .file_format(Format::Custom(Arc::new(|r| {
+            format!("{}: {}\n", r.level(), r.message)
+        })))
+        .create();
+    custom.open_log_file().unwrap();
+    log_record(&custom, log::Level::Info, "tests", "custom");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "INFO: custom\n");
+}
+
+#[test]
+fn test_file_level() {
+    let path = temp_path("file_level.log");
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .default_filter(LevelFilter::Trace)
+        .log_to_file()
+        .log_filname(&path)
+        .file_level(LevelFilter::Warn)
+        .console_level(LevelFilter::Off)
+        .memory_sink(1024)
+        .create();
+    logger.open_log_file().unwrap();
+
+    log_record(&logger, log::Level::Debug, "tests", "verbose");
+    log_record(&logger, log::Level::Error, "tests", "important");
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("verbose"));
+    assert!(contents.contains("important"));
+    assert!(memory_contents(&logger).contains("verbose"));
+}
+
+#[test]
+fn test_error_log_file() {
+    let path = temp_path("split/app.log");
+    let errors = temp_path("split/errors.log");
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .log_to_file()
+        .log_filname(&path)
+        .error_log_file(&errors)
+        .create();
+    logger.open_log_file().unwrap();
+
+    log_record(&logger, log::Level::Info, "tests", "noise");
+    log_record(&logger, log::Level::Warn, "tests", "careful");
+    log_record(&logger, log::Level::Error, "tests", "failed");
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(contents.lines().count(), 3);
+    let errors = std::fs::read_to_string(&errors).unwrap();
+    assert!(!errors.contains("noise"));
+    assert!(errors.contains("careful"));
+    assert!(errors.contains("failed"));
+}
+
+#[test]
+fn test_key_values() {
+    let fields: &[(&str, i32)] = &[("user", 42), ("retries", 3)];
+    let record = log::Record::builder()
+        .level(log::Level::Info)
+        .target("tests")
+        .args(format_args!("logged in"))
+        .key_values(&fields)
+        .build();
+
+    let key_values = crate::format::key_values(&record);
+    let formatted = FormattedRecord {
+        record: &record,
+        timestamp: "time",
+        target: "tests",
+        message: "logged in",
+        context: &[],
+        fields: &[],
+        key_values: &key_values,
+        output: "",
+    };
+
+    let logger = crate::Builder::new().use_color(false).create();
+    let line = logger.format_line(&formatted, false);
+    assert_eq!(line, "[time INFO  tests] logged in user=42 retries=3\n");
+
+    let json = Format::Json.render(&formatted).unwrap();
+    assert!(json.ends_with(",\"fields\":{\"user\":\"42\",\"retries\":\"3\"}}\n"));
+}
+
+#[test]
+fn test_thread_context() {
+    let logger = crate::Builder::new()
+        .add_context("app", "tests")
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    crate::context::scope("request_id", 7, || {
+        let _user = crate::context::push("user", "bob");
+        log_record(&logger, log::Level::Info, "tests", "inner");
+    });
+    log_record(&logger, log::Level::Info, "tests", "outer");
+
+    let contents = memory_contents(&logger);
+    assert!(contents.contains("tests] [app=tests request_id=7 user=bob] inner\n"));
+    assert!(contents.ends_with("tests] [app=tests] outer\n"));
+}
+
+#[test]
+fn test_show_thread() {
+    let logger = crate::Builder::new()
+        .show_thread(true)
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+    let logger = Arc::new(logger);
+
+    let named = logger.clone();
+    std::thread::Builder::new()
+        .name("worker".into())
+        .spawn(move || log_record(&named, log::Level::Info, "tests", "named"))
+        .unwrap()
+        .join()
+        .unwrap();
+    let unnamed = logger.clone();
+    std::thread::spawn(move || log_record(&unnamed, log::Level::Info, "tests", "unnamed"))
+        .join()
+        .unwrap();
+
+    let contents = memory_contents(&logger);
+    assert!(contents.contains("INFO  thread=worker tests] named\n"));
+    let line = contents.lines().last().unwrap();
+    let id = line
+        .split("thread=")
+        .nth(1)
+        .and_then(|s| s.split(' ').next())
+        .unwrap();
+    assert!(id.parse::<u64>().is_ok(), "{line}");
+}
+
+#[test]
+fn test_show_pid() {
+    let logger = crate::Builder::new()
+        .show_pid(true)
+        .show_thread(true)
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    log_record(&logger, log::Level::Info, "tests", "msg");
+
+    let contents = memory_contents(&logger);
+    let pid = std::process::id();
+    assert!(contents.contains(&format!("INFO  pid={pid} thread=")));
+
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .pid_in_filename(true)
+        .create();
+    let path = crate::expand_file_name(&logger.log_filename);
+    let name = path.file_name().unwrap().to_str().unwrap();
+    assert!(name.ends_with(&format!("-{pid}.log")));
+}
+
+#[test]
+fn test_show_hostname() {
+    let logger = crate::Builder::new()
+        .show_hostname(true)
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    let hostname = crate::hostname().unwrap();
+    assert!(!hostname.is_empty());
+
+    log_record(&logger, log::Level::Info, "tests", "msg");
+    let contents = memory_contents(&logger);
+    assert!(contents.contains(&format!("INFO  hostname={hostname} tests] msg")));
+
+    let record = log::Record::builder()
+        .level(log::Level::Info)
+        .args(format_args!("msg"))
+        .build();
+    let fields = logger.record_fields(&record);
+    let json = Format::Json
+        .render(&FormattedRecord {
+            record: &record,
+            timestamp: "time",
+            target: "tests",
+            message: "msg",
+            context: &[],
+            fields: &fields,
+            key_values: &[],
+            output: "",
+        })
+        .unwrap();
+    assert!(json.contains(&format!(",\"hostname\":\"{hostname}\"")));
+}
+
+#[test]
+fn test_show_location() {
+    let logger = crate::Builder::new()
+        .show_location(log::Level::Debug)
+        .default_filter(LevelFilter::Trace)
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    for level in [log::Level::Info, log::Level::Debug] {
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .level(level)
+                .target("tests")
+                .file(Some("src/net/client.rs"))
+                .line(Some(42))
+                .args(format_args!("msg"))
+                .build(),
+        );
+    }
+
+    let contents = memory_contents(&logger);
+    assert!(contents.contains("INFO  tests] msg\n"));
+    assert!(contents.contains("DEBUG location=src/net/client.rs:42 tests] msg\n"));
+}
+
+#[test]
+fn test_target_width() {
+    assert_eq!(crate::fit_width("net", 6), "net   ");
+    assert_eq!(crate::fit_width("wgpu::instance", 10), "…:instance");
+    assert_eq!(crate::fit_width("client", 6), "client");
+
+    let logger = crate::Builder::new()
+        .target_width(8)
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    log_record(&logger, log::Level::Info, "net", "short");
+    log_record(&logger, log::Level::Info, "wgpu_hal::vulkan", "long");
+
+    let contents = memory_contents(&logger);
+    assert!(contents.contains("INFO  net     ] short\n"));
+    assert!(contents.contains("INFO  …:vulkan] long\n"));
+}
+
+#[test]
+fn test_abbreviate_targets() {
+    assert_eq!(
+        crate::abbreviate("wgpu_hal::vulkan::instance"),
+        "w::v::instance"
+    );
+    assert_eq!(crate::abbreviate("tests"), "tests");
+
+    let logger = crate::Builder::new()
+        .add_path_prefix_strip("my_app")
+        .abbreviate_targets(true)
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    log_record(&logger, log::Level::Info, "my_app::net::client", "msg");
+
+    let contents = memory_contents(&logger);
+    assert!(contents.ends_with("INFO  n::client] msg\n"));
+}
+
+#[test]
+fn test_multiline_style() {
+    let mut logger = crate::Builder::new()
+        .multiline_style(MultilineStyle::Align)
+        .use_color(false)
+        .create();
+
+    let line = console_line(&logger, log::Level::Error, "first\nsecond");
+    assert_eq!(
+        line,
+        "[time ERROR tests] first\n                   second\n"
+    );
+
+    logger.set_multiline_style(MultilineStyle::Prefix);
+    let line = console_line(&logger, log::Level::Error, "first\nsecond");
+    assert_eq!(
+        line,
+        "[time ERROR tests] first\n[time ERROR tests] second\n"
+    );
+}
+
+#[test]
+fn test_max_message_length() {
+    let logger = crate::Builder::new()
+        .max_message_length(5)
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    log_record(&logger, log::Level::Info, "tests", "short");
+    log_record(&logger, log::Level::Info, "tests", "ünïcödé");
+
+    let contents = memory_contents(&logger);
+    assert!(contents.contains("tests] short\n"));
+    assert!(contents.ends_with("tests] ünïcö…\n"));
+}
+
+#[test]
+fn test_redaction() {
+    struct Chained(Arc<Mutex<Vec<String>>>);
+
+    impl log::Log for Chained {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let key_values = crate::format::key_values(record);
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{} {key_values:?}", record.args()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let hook_seen = Arc::clone(&seen);
+
+    let fields: &[(&str, &str)] = &[("token", "hunter2")];
+    let logger = crate::Builder::new()
+        .redact_keyword("hunter2")
+        .add_context("password", "hunter2")
+        .use_color(false)
+        .memory_sink(1024)
+        .add_output_hook(Box::new(move |_, record| {
+            hook_seen.lock().unwrap().push(record.args().to_string());
+        }))
+        .chain(Box::new(Chained(Arc::clone(&seen))))
+        .create();
+
+    log::Log::log(
+        &logger,
+        &log::Record::builder()
+            .level(log::Level::Info)
+            .target("tests")
+            .args(format_args!("logged in with hunter2"))
+            .key_values(&fields)
+            .build(),
+    );
+
+    let contents = memory_contents(&logger);
+    assert!(!contents.contains("hunter2"));
+    assert!(contents
+        .ends_with("tests] [password=[REDACTED]] logged in with [REDACTED] token=[REDACTED]\n"));
+
+    //Hooks and chained loggers get the redacted record
+    assert_eq!(
+        *seen.lock().unwrap(),
+        [
+            "logged in with [REDACTED]",
+            "logged in with [REDACTED] [(\"token\", \"[REDACTED]\")]"
+        ]
+    );
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_redaction_pattern() {
+    let logger = crate::Builder::new()
+        .redact_pattern(r"Bearer [A-Za-z0-9._-]+")
+        .unwrap()
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    log_record(
+        &logger,
+        log::Level::Info,
+        "tests",
+        "Authorization: Bearer abc.def-1",
+    );
+
+    let contents = memory_contents(&logger);
+    assert!(contents.ends_with("tests] Authorization: [REDACTED]\n"));
+}
+
+#[test]
+fn test_suppress_duplicates() {
+    let logger = crate::Builder::new()
+        .suppress_duplicates(true)
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    for _ in 0..4 {
+        log_record(&logger, log::Level::Warn, "tests", "spam");
+    }
+    log_record(&logger, log::Level::Info, "tests", "done");
+    log_record(&logger, log::Level::Info, "tests", "done");
+    log::Log::flush(&logger);
+
+    let contents = memory_contents(&logger);
+    let messages: Vec<&str> = contents
+        .lines()
+        .map(|l| l.split("] ").nth(1).unwrap())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "spam",
+            "last message repeated 3 times",
+            "done",
+            "last message repeated 1 times"
+        ]
+    );
+    assert!(contents.contains("WARN  tests] last message repeated 3 times"));
+
+    //Duplicates from several threads are all counted
+    let logger = Arc::new(
+        crate::Builder::new()
+            .suppress_duplicates(true)
+            .use_color(false)
+            .memory_sink(1024)
+            .create(),
+    );
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let logger = logger.clone();
+            std::thread::spawn(move || {
+                for _ in 0..25 {
+                    log_record(&logger, log::Level::Warn, "tests", "spam");
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    log::Log::flush(&*logger);
+
+    let contents = memory_contents(&logger);
+    assert_eq!(contents.lines().count(), 2);
+    assert!(contents.ends_with("] last message repeated 99 times\n"));
+}
+
+#[test]
+fn test_sampling() {
+    let logger = crate::Builder::new()
+        .default_filter(LevelFilter::Trace)
+        .sample("tests::hot", Sampling::EveryNth(3))
+        .sample("tests::never", Sampling::Probability(0.0))
+        .use_color(false)
+        .memory_sink(4096)
+        .create();
+
+    for i in 0..7 {
+        log_record(
+            &logger,
+            log::Level::Trace,
+            "tests::hot::loop",
+            &format!("{i}"),
+        );
+        log_record(&logger, log::Level::Debug, "tests::never", "dropped");
+    }
+    //Only verbose levels are sampled
+    log_record(&logger, log::Level::Info, "tests::never", "kept");
+
+    let contents = memory_contents(&logger);
+    let messages: Vec<&str> = contents
+        .lines()
+        .map(|l| l.split("] ").nth(1).unwrap())
+        .collect();
+    assert_eq!(messages, ["0", "3", "6", "kept"]);
+}
+
+#[test]
+fn test_recent() {
+    let logger = crate::Builder::new()
+        .keep_recent(2)
+        .use_color(false)
+        .create();
+    let handle = LoggerHandle::new(Arc::new(logger));
+
+    for msg in ["first", "second", "third"] {
+        log_record(handle.logger(), log::Level::Info, "tests", msg);
+    }
+
+    let recent = handle.recent();
+    assert_eq!(recent.len(), 2);
+    assert!(recent[0].ends_with("tests] second\n"));
+    assert!(recent[1].ends_with("tests] third\n"));
+}
+
+#[test]
+fn test_buffer_until_error() {
+    let path = temp_path("buffer_until_error.log");
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&path)
+        .default_filter(LevelFilter::Trace)
+        .buffer_until_error(2)
+        .use_color(false)
+        .create();
+    logger.open_log_file().unwrap();
+
+    log_record(&logger, log::Level::Debug, "tests", "dropped");
+    log_record(&logger, log::Level::Info, "tests", "started");
+    log_record(&logger, log::Level::Debug, "tests", "connecting");
+    log_record(&logger, log::Level::Trace, "tests", "handshake");
+    log::Log::flush(&logger);
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(contents.lines().count(), 1);
+
+    log_record(&logger, log::Level::Error, "tests", "failed");
+    log::Log::flush(&logger);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let messages: Vec<&str> = contents
+        .lines()
+        .map(|l| l.split("] ").nth(1).unwrap())
+        .collect();
+    assert_eq!(messages, ["started", "connecting", "handshake", "failed"]);
+}
+
+#[test]
+fn test_capture() {
+    let capture = crate::test::capture();
+    log::warn!(target: "tests", "Request timed out");
+
+    let other = std::thread::spawn(|| {
+        let capture = crate::test::capture();
+        log::error!("Other thread");
+        capture.records().len()
+    });
+    assert_eq!(other.join().unwrap(), 1);
+
+    capture.assert_logged(log::Level::Warn, "timed out");
+    capture.assert_not_logged(log::Level::Error, "timed out");
+    capture.assert_not_logged(log::Level::Error, "Other thread");
+    assert_eq!(capture.records()[0].target, "tests");
+
+    capture.clear();
+    assert!(capture.records().is_empty());
+}
+
+#[test]
+fn test_chain() {
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl log::Log for Collect {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let logger = crate::Builder::new()
+        .add_crate_filter("noisy", LevelFilter::Error)
+        .chain(Box::new(Collect(records.clone())))
+        .use_color(false)
+        .memory_sink(1024)
+        .create();
+
+    log_record(&logger, log::Level::Info, "tests", "forwarded");
+    log_record(&logger, log::Level::Info, "noisy", "filtered");
+
+    assert_eq!(*records.lock().unwrap(), ["forwarded"]);
+    assert!(memory_contents(&logger).contains("forwarded"));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing_events() {
+    let capture = crate::test::capture();
+    tracing::warn!(target: "tests", attempt = 3, "Request timed out");
+
+    capture.assert_logged(log::Level::Warn, "Request timed out");
+    assert!(capture.records()[0].message.contains("attempt=3"));
+}
+
+#[cfg(feature = "syslog")]
+#[test]
+fn test_syslog() {
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    server
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    let syslog = SyslogSink::udp(server.local_addr().unwrap())
+        .unwrap()
+        .facility(Facility::Local0)
+        .app_name("my tests");
+
+    let logger = crate::Builder::new()
+        .add_sink(Box::new(syslog))
+        .use_color(false)
+        .create();
+    log_record(&logger, log::Level::Warn, "tests::net", "timed out");
+
+    let mut buffer = [0; 1024];
+    let len = server.recv(&mut buffer).unwrap();
+    let message = std::str::from_utf8(&buffer[..len]).unwrap();
+
+    //Local0 (16) * 8 + warning (4)
+    assert!(message.starts_with("<132>1 "), "{message}");
+    let pid = std::process::id();
+    assert!(message.ends_with(&format!(" mytests {pid} - - tests::net: timed out")));
+}
+
+#[cfg(all(feature = "journald", unix))]
+#[test]
+fn test_journald() {
+    let path = temp_path("journal.socket");
+    let _ = std::fs::remove_file(&path);
+    let journal = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+    let sink = crate::JournaldSink::with_socket(&path)
+        .unwrap()
+        .identifier("tests");
+
+    let logger = crate::Builder::new().add_sink(Box::new(sink)).create();
+    log::Log::log(
+        &logger,
+        &log::Record::builder()
+            .level(log::Level::Error)
+            .target("tests::net")
+            .file(Some("src/net.rs"))
+            .line(Some(42))
+            .args(format_args!("first\nsecond"))
+            .build(),
+    );
+
+    let mut buffer = [0; 1024];
+    let len = journal.recv(&mut buffer).unwrap();
+    let mut expected = b"MESSAGE\n".to_vec();
+    expected.extend_from_slice(&12u64.to_le_bytes());
+    expected.extend_from_slice(
+        b"first\nsecond\nPRIORITY=3\nTARGET=tests::net\nCODE_FILE=src/net.rs\nCODE_LINE=42\nSYSLOG_IDENTIFIER=tests\n",
+    );
+    assert_eq!(&buffer[..len], &expected[..]);
+}
+
+#[cfg(all(feature = "os_log", target_vendor = "apple"))]
+#[test]
+fn test_os_log() {
+    let logger = crate::Builder::new()
+        .add_sink(Box::new(crate::OsLogSink::new("com.lunar_logger.tests")))
+        .create();
+
+    log_record(&logger, log::Level::Info, "tests", "first");
+    log_record(&logger, log::Level::Error, "tests", "second");
+}
+
+#[cfg(feature = "gelf")]
+#[test]
+fn test_gelf() {
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    server
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    let gelf = GelfSink::udp(server.local_addr().unwrap())
+        .unwrap()
+        .host("test-host")
+        .field("environment", "tests")
+        .field("request id", "1")
+        .field("id", "2")
+        .chunk_size(512);
+
+    let logger = crate::Builder::new()
+        .add_sink(Box::new(gelf))
+        .use_color(false)
+        .create();
+    log_record(&logger, log::Level::Warn, "tests", "short\nsecond line");
+
+    let mut buffer = [0; 1024];
+    let len = server.recv(&mut buffer).unwrap();
+    let message = std::str::from_utf8(&buffer[..len]).unwrap();
+    assert!(message.starts_with(
+        "{\"version\":\"1.1\",\"host\":\"test-host\",\"short_message\":\"short\",\"timestamp\":"
+    ));
+    assert!(message.ends_with(
+        ",\"level\":4,\"_target\":\"tests\",\"full_message\":\"short\\nsecond line\",\"_environment\":\"tests\",\"_request_id\":\"1\",\"_id_\":\"2\"}"
+    ));
+
+    log_record(&logger, log::Level::Info, "tests", &"x".repeat(1000));
+    let mut chunks = Vec::new();
+    for _ in 0..3 {
+        let len = server.recv(&mut buffer).unwrap();
+        assert!(len <= 512);
+        assert_eq!(buffer[..2], [0x1e, 0x0f]);
+        assert_eq!(buffer[11], 3);
+        chunks.push((buffer[10], buffer[12..len].to_vec()));
+    }
+    chunks.sort();
+    let message: Vec<u8> = chunks.into_iter().flat_map(|(_, data)| data).collect();
+    let message = String::from_utf8(message).unwrap();
+    assert!(message.contains(&format!("\"short_message\":\"{}\"", "x".repeat(1000))));
+    assert!(message.ends_with("\"_environment\":\"tests\",\"_request_id\":\"1\",\"_id_\":\"2\"}"));
+}
+
+///Accepts a single HTTP request and returns its head and body
+#[cfg(feature = "http")]
+fn http_server() -> (String, std::thread::JoinHandle<(String, String)>) {
+    use std::io::{BufRead, Read};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let thread = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = std::io::BufReader::new(stream);
+
+        let mut head = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            head.push_str(&line);
+        }
+
+        let length = head
+            .lines()
+            .find_map(|l| {
+                l.to_lowercase()
+                    .strip_prefix("content-length: ")
+                    .map(str::to_owned)
+            })
+            .unwrap()
+            .parse()
+            .unwrap();
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+
+        (head, String::from_utf8(body).unwrap())
+    });
+
+    (url, thread)
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_loki() {
+    assert_eq!(crate::http::base64(b"user:key"), "dXNlcjprZXk=");
+
+    let (url, server) = http_server();
+    let loki = crate::LokiSink::builder(&url)
+        .basic_auth("user", "key")
+        .label("service", "tests")
+        .build();
+
+    let logger = crate::Builder::new()
+        .add_sink(Box::new(loki))
+        .time_format("time")
+        .use_color(false)
+        .create();
+    log_record(&logger, log::Level::Info, "tests", "first");
+    log_record(&logger, log::Level::Info, "tests", "second");
+    log_record(&logger, log::Level::Error, "tests", "third");
+    log::Log::flush(&logger);
+
+    let (head, body) = server.join().unwrap();
+    assert!(head.starts_with("POST /loki/api/v1/push HTTP/1.1\r\n"));
+    assert!(head.contains("Authorization: Basic dXNlcjprZXk=\r\n"));
+
+    assert!(body.starts_with(
+        "{\"streams\":[{\"stream\":{\"level\":\"error\",\"service\":\"tests\",\"target\":\"tests\"},\"values\":[[\""
+    ));
+    assert!(body.contains("\",\"[time ERROR tests] third\"]]},{\"stream\":{\"level\":\"info\","));
+    assert!(body.contains("\",\"[time INFO  tests] first\"],[\""));
+    assert!(body.ends_with("\",\"[time INFO  tests] second\"]]}]}"));
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_webhook() {
+    let records = ["{\"a\":1}".to_owned(), "{\"b\":2}".to_owned()];
+    assert_eq!(
+        crate::webhook::body(crate::WebhookFormat::Ndjson, &records),
+        ("application/x-ndjson", "{\"a\":1}\n{\"b\":2}\n".to_owned())
+    );
+
+    let (url, server) = http_server();
+    let webhook = crate::WebhookSink::builder(&format!("{url}/logs"))
+        .header("X-Api-Key", "secret")
+        .build();
+
+    let logger = crate::Builder::new()
+        .add_sink(Box::new(webhook))
+        .time_format("time")
+        .create();
+    log_record(&logger, log::Level::Info, "tests", "first");
+    log_record(&logger, log::Level::Warn, "tests", "second");
+    log::Log::flush(&logger);
+
+    let (head, body) = server.join().unwrap();
+    assert!(head.starts_with("POST /logs HTTP/1.1\r\n"));
+    assert!(head.contains("X-Api-Key: secret\r\n"));
+    assert!(head.contains("Content-Type: application/json\r\n"));
+    assert_eq!(
+        body,
+        "[{\"timestamp\":\"time\",\"level\":\"INFO\",\"target\":\"tests\",\"message\":\"first\"},{\"timestamp\":\"time\",\"level\":\"WARN\",\"target\":\"tests\",\"message\":\"second\"}]"
+    );
+}
+
+#[cfg(any(feature = "net", feature = "syslog"))]
+#[test]
+fn test_tcp_reconnect() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut connection =
+        crate::tcp::TcpConnection::connect(vec![listener.local_addr().unwrap()]).unwrap();
+    drop(listener);
+
+    //Writes fail once the closed connection is noticed
+    assert!((0..100).any(|_| connection.write_all(b"record\n").is_err()));
+    //Reconnecting fails, and isn't retried for the next records
+    assert!(connection.write_all(b"record\n").is_err());
+    let start = std::time::Instant::now();
+    for _ in 0..100 {
+        assert!(connection.write_all(b"record\n").is_ok());
+    }
+    assert!(start.elapsed() < std::time::Duration::from_millis(100));
+}
+
+#[cfg(feature = "net")]
+#[test]
+fn test_network_sink() {
+    use std::io::BufRead;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let sink = crate::NetworkSink::tcp(listener.local_addr().unwrap()).unwrap();
+    let logger = crate::Builder::new()
+        .add_sink(Box::new(sink))
+        .time_format("time")
+        .create();
+
+    let (stream, _) = listener.accept().unwrap();
+    log_record(&logger, log::Level::Info, "tests", "first");
+    let mut line = String::new();
+    std::io::BufReader::new(&stream)
+        .read_line(&mut line)
+        .unwrap();
+    assert_eq!(line, "[time INFO  tests] first\n");
+
+    //The first write after the listener closes the connection fails, the next one reconnects
+    drop(stream);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    for _ in 0..3 {
+        log_record(&logger, log::Level::Info, "tests", "lost");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    let (stream, _) = listener.accept().unwrap();
+    log_record(&logger, log::Level::Info, "tests", "second");
+    let mut line = String::new();
+    let mut reader = std::io::BufReader::new(&stream);
+    reader.read_line(&mut line).unwrap();
+    while line.ends_with("lost\n") {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+    }
+    assert_eq!(line, "[time INFO  tests] second\n");
+}
+
+#[cfg(feature = "otlp")]
+#[test]
+fn test_otlp() {
+    let (url, server) = http_server();
+    let otlp = crate::OtlpSink::builder(&url).service_name("tests").build();
+
+    let logger = crate::Builder::new().add_sink(Box::new(otlp)).create();
+    log::Log::log(
+        &logger,
+        &log::Record::builder()
+            .level(log::Level::Warn)
+            .target("tests")
+            .args(format_args!("low disk"))
+            .file(Some("src/tests.rs"))
+            .line(Some(7))
+            .key_values(&[("free", "3%")])
+            .build(),
+    );
+    log::Log::flush(&logger);
+
+    let (head, body) = server.join().unwrap();
+    assert!(head.starts_with("POST /v1/logs HTTP/1.1\r\n"));
+    assert!(body.starts_with(
+        "{\"resourceLogs\":[{\"resource\":{\"attributes\":[{\"key\":\"service.name\",\"value\":{\"stringValue\":\"tests\"}}]},\"scopeLogs\":[{\"scope\":{\"name\":\"lunar-logger\""
+    ));
+    assert!(body.contains(
+        "\"severityNumber\":13,\"severityText\":\"WARN\",\"body\":{\"stringValue\":\"low disk\"},\"attributes\":[{\"key\":\"target\",\"value\":{\"stringValue\":\"tests\"}},{\"key\":\"code.filepath\",\"value\":{\"stringValue\":\"src/tests.rs\"}},{\"key\":\"code.lineno\",\"value\":{\"intValue\":\"7\"}},{\"key\":\"free\",\"value\":{\"stringValue\":\"3%\"}}]}]}]}]}"
+    ));
+}
+
+#[cfg(feature = "sentry")]
+#[test]
+fn test_sentry() {
+    let kvs = [("user".to_owned(), "42".to_owned())];
+    let fields = [("thread", "main".to_owned())];
+    let record = log::Record::builder()
+        .level(log::Level::Error)
+        .target("tests")
+        .build();
+    let formatted = FormattedRecord {
+        record: &record,
+        timestamp: "time",
+        target: "tests",
+        message: "failed to save",
+        context: &[],
+        fields: &fields,
+        key_values: &kvs,
+        output: "",
+    };
+
+    let event = crate::sentry::event(&formatted);
+    assert_eq!(event.level, sentry_core::Level::Error);
+    assert_eq!(event.logger.as_deref(), Some("tests"));
+    assert_eq!(event.message.as_deref(), Some("failed to save"));
+    assert_eq!(event.tags["thread"], "main");
+    assert_eq!(event.extra["user"], "42");
+
+    let breadcrumb = crate::sentry::breadcrumb(&formatted);
+    assert_eq!(breadcrumb.category.as_deref(), Some("tests"));
+    assert_eq!(breadcrumb.data["user"], "42");
+}
+
+#[test]
+fn test_recent_text() {
+    let logger = crate::Builder::new()
+        .keep_recent(2)
+        .time_format("time")
+        .create();
+    let handle = LoggerHandle::new(Arc::new(logger));
+
+    for msg in ["first", "second", "third"] {
+        log_record(handle.logger(), log::Level::Info, "tests", msg);
+    }
+
+    assert_eq!(
+        handle.recent_text(),
+        "[time INFO  tests] second\n[time INFO  tests] third\n"
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn test_default_log_name() {
+    let dir = crate::default_log_dir().unwrap();
+    assert!(dir.ends_with("lunar-logging"));
+    #[cfg(target_os = "linux")]
+    assert!(dir.is_absolute());
+    assert_eq!(
+        crate::log_dir_or_fallback(None).unwrap(),
+        std::env::temp_dir().join("lunar-logging")
+    );
+
+    let name = crate::expand_file_name(&crate::generate_log_name(true).unwrap());
+    assert_eq!(name.parent().unwrap(), dir);
+    let file = name.file_name().unwrap().to_str().unwrap();
+    assert!(file.starts_with("log-"));
+    assert!(file.ends_with(&format!("-{}.log", std::process::id())));
+}
+
+#[test]
+fn test_file_name_template() {
+    let dir = temp_path("template");
+    let _ = std::fs::remove_dir_all(&dir);
+    let handle = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&dir.join("{app}-{date}-{pid}.log"))
+        .create();
+    handle.open_log_file().unwrap();
+
+    let app = std::env::current_exe().unwrap();
+    let app = app.file_stem().unwrap().to_str().unwrap();
+    let date = chrono::Local::now().format("%Y-%m-%d");
+    let pid = std::process::id();
+    let name = format!("{app}-{date}-{pid}.log");
+    assert!(dir.join(&name).is_file());
+    #[cfg(unix)]
+    assert_eq!(
+        std::fs::read_link(dir.join("latest.log")).unwrap(),
+        std::path::Path::new(&name)
+    );
+
+    assert_eq!(
+        crate::expand_file_name(std::path::Path::new("plain.log")),
+        std::path::Path::new("plain.log")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = temp_path("file_mode.log");
+    std::fs::write(&path, "").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&path)
+        .file_mode(0o600)
+        .create();
+    logger.open_log_file().unwrap();
+
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_write_failure_policy() {
+    let fallback = temp_path("fallback.log");
+
+    //Every write to /dev/full fails with "No space left on device"
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(Path::new("/dev/full"))
+        .write_failure_policy(crate::WriteFailurePolicy::Fallback(fallback.clone()))
+        .create();
+    logger.open_log_file().unwrap();
+
+    log_record(&logger, log::Level::Info, "tests", "first");
+    log_record(&logger, log::Level::Info, "tests", "second");
+
+    let contents = std::fs::read_to_string(&fallback).unwrap();
+    assert!(contents.contains("tests] first"));
+    assert!(contents.contains("tests] second"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_on_error() {
+    let errors = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let handler_errors = Arc::clone(&errors);
+
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(Path::new("/dev/full"))
+        .on_error(Box::new(move |e| {
+            handler_errors.lock().unwrap().push(e.to_string());
+        }))
+        .create();
+    logger.open_log_file().unwrap();
+
+    log_record(&logger, log::Level::Info, "tests", "first");
+    log_record(&logger, log::Level::Info, "tests", "second");
+
+    //Retried writes are reported once, until one of them succeeds
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("Failed to write to a log file"));
+}
+
+#[test]
+fn test_append_to_file() {
+    let path = temp_path("append.log");
+    std::fs::write(&path, "previous run\n").unwrap();
+
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&path)
+        .append_to_file(true)
+        .create();
+    logger.open_log_file().unwrap();
+    log_record(&logger, log::Level::Info, "tests", "current run");
+    drop(logger);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.starts_with("previous run\n"));
+    assert!(contents.contains("tests] current run"));
+
+    //A fresh file is started by default
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&path)
+        .create();
+    logger.open_log_file().unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+}
+
+#[test]
+fn test_sync_mode() {
+    for (name, mode) in [
+        ("sync_every.log", crate::SyncMode::EveryRecord),
+        (
+            "sync_interval.log",
+            crate::SyncMode::Interval(std::time::Duration::ZERO),
+        ),
+    ] {
+        let path = temp_path(name);
+        let errors = Arc::new(std::sync::Mutex::new(0));
+        let handler_errors = Arc::clone(&errors);
+
+        let logger = crate::Builder::new()
+            .log_to_file()
+            .log_filname(&path)
+            .sync_mode(mode)
+            .on_error(Box::new(move |_| *handler_errors.lock().unwrap() += 1))
+            .create();
+        logger.open_log_file().unwrap();
+        log_record(&logger, log::Level::Info, "tests", "synced");
+
+        assert_eq!(*errors.lock().unwrap(), 0);
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("tests] synced"));
+    }
+}
+
+#[test]
+fn test_session_banner() {
+    let path = temp_path("session_banner.log");
+
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&path)
+        .session_banner(true)
+        .add_context("app", "banner_test")
+        .add_context("version", "1.2.3")
+        .add_crate_filter("tests", LevelFilter::Debug)
+        .create();
+    logger.open_log_file().unwrap();
+    log_record(&logger, log::Level::Info, "tests", "after banner");
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.starts_with("================================ Session started"));
+    assert!(contents.contains("\nApp: banner_test 1.2.3\n"));
+    assert!(contents.contains(&format!("\nPID: {}\n", std::process::id())));
+    assert!(contents.contains("│ tests │ Crate   │ Debug │"));
+    assert!(contents.trim_end().ends_with("after banner"));
+}
+
+#[cfg(feature = "audit")]
+#[test]
+fn test_hash_chain() {
+    let path = temp_path("hash_chain.log");
+
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&path)
+        .hash_chain(true)
+        .session_banner(true)
+        .create();
+    logger.open_log_file().unwrap();
+    for message in ["first", "second", "third"] {
+        log_record(&logger, log::Level::Info, "tests", message);
+    }
+
+    let status = crate::verify_hash_chain(&path).unwrap();
+    let crate::ChainStatus::Intact(lines) = status else {
+        panic!("{status:?}");
+    };
+
+    //Modifying a line breaks the link of the next one
+    let contents = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, contents.replace("second", "changed")).unwrap();
+    assert_eq!(
+        crate::verify_hash_chain(&path).unwrap(),
+        crate::ChainStatus::Broken(lines)
+    );
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encrypted_file_sink() {
+    let path = temp_path("encrypted.log");
+    let (secret, public) = crate::generate_key_pair();
+
+    //Two sessions appending to the same file
+    for message in ["first", "second"] {
+        let logger = crate::Builder::new()
+            .add_sink(Box::new(
+                crate::EncryptedFileSink::new(&path, public).unwrap(),
+            ))
+            .create();
+        log_record(&logger, log::Level::Info, "tests", message);
+    }
+
+    let contents = std::fs::read(&path).unwrap();
+    assert!(!contents.windows(5).any(|w| w == b"first"));
+
+    let text = crate::decrypt_log(&path, secret).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("tests] first"));
+    assert!(lines[1].ends_with("tests] second"));
+
+    let (other_secret, _) = crate::generate_key_pair();
+    assert!(crate::decrypt_log(&path, other_secret).is_err());
+}
+
+#[test]
+fn test_prune_logs() {
+    let dir = temp_path("prune_logs");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    //Logs of previous runs, from oldest to newest
+    let old = ["run-1.log", "run-2.log", "run-3.log"];
+    for (i, name) in old.iter().enumerate() {
+        let file = std::fs::File::create(dir.join(name)).unwrap();
+        let age = std::time::Duration::from_secs(60 * (10 - i as u64));
+        file.set_modified(std::time::SystemTime::now() - age)
+            .unwrap();
+    }
+    let rotated = std::fs::File::create(dir.join("run-1.log.1.gz")).unwrap();
+    rotated
+        .set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(600))
+        .unwrap();
+    std::fs::write(dir.join("other.txt"), "").unwrap();
+    std::fs::write(dir.join("other.log"), "").unwrap();
+    std::fs::write(dir.join("run-backup.log"), "").unwrap();
+
+    let logger = crate::Builder::new()
+        .log_to_file()
+        .log_filname(&dir.join("run-{pid}.log"))
+        .prune_logs(3, std::time::Duration::from_secs(3600))
+        .create();
+    logger.open_log_file().unwrap();
+
+    //The current file and the two newest previous ones are kept
+    assert!(!dir.join("run-1.log").exists());
+    assert!(!dir.join("run-1.log.1.gz").exists());
+    assert!(dir.join("run-2.log").exists());
+    assert!(dir.join("run-3.log").exists());
+    assert!(dir.join(format!("run-{}.log", std::process::id())).exists());
+    assert!(dir.join("other.txt").exists());
+    //Unrelated log files are never deleted
+    assert!(dir.join("other.log").exists());
+    assert!(dir.join("run-backup.log").exists());
+}
+
+#[cfg(feature = "timezone")]
+#[test]
+fn test_timezone() {
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .time_format("%Z")
+        .timezone("America/New_York")
+        .unwrap()
+        .memory_sink(1024)
+        .create();
+    log_record(&logger, log::Level::Info, "tests", "msg");
+
+    //Eastern standard or daylight time, depending on the date
+    let contents = memory_contents(&logger);
+    assert!(contents.starts_with("[EST INFO ") || contents.starts_with("[EDT INFO "));
+
+    assert!(matches!(
+        crate::Builder::new().timezone("Mars/Olympus_Mons"),
+        Err(LoggerError::InvalidTimezone(_))
+    ));
+}
+
+#[test]
+fn test_uptime_timestamps() {
+    let logger = crate::Builder::new()
+        .use_color(false)
+        .uptime_timestamps(true)
+        .memory_sink(1024)
+        .create();
+    //The time is measured from the first record, as the logger is not enabled
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    log_record(&logger, log::Level::Info, "tests", "msg");
+
+    let contents = memory_contents(&logger);
+    let (time, rest) = contents[1..].split_once("s INFO  ").unwrap();
+    assert_eq!(time.len(), 10);
+    assert!(time.trim_start().parse::<f64>().unwrap() < 0.2);
+    assert!(rest.starts_with("tests] msg"));
 }
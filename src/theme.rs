@@ -0,0 +1,253 @@
+use std::fmt;
+
+///Color used in the console output
+///
+///[`Rgb`](Color::Rgb) and [`Ansi256`](Color::Ansi256) colors are converted to the closest color
+///the terminal supports, based on the `COLORTERM`, `TERM` and `WT_SESSION` environment variables
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    ///Default color of the terminal
+    Default,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    ///Color from the 256 color palette
+    Ansi256(u8),
+    ///24-bit color
+    Rgb(u8, u8, u8),
+}
+
+///The 16 basic colors, in the order of their palette indices
+const BASIC: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+///Approximate values of the basic colors, as displayed by xterm
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+///Levels of the 6x6x6 color cube of the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+///Colors supported by the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ColorDepth {
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    ///Detects the colors supported by the terminal from the environment
+    pub(crate) fn detect(var: impl Fn(&str) -> Option<String>) -> Self {
+        //Windows Terminal supports 24-bit colors, but doesn't set `COLORTERM`
+        if var("COLORTERM").is_some_and(|v| v == "truecolor" || v == "24bit")
+            || var("WT_SESSION").is_some()
+        {
+            Self::TrueColor
+        } else if var("TERM").is_some_and(|v| v.contains("256color")) {
+            Self::Ansi256
+        } else {
+            Self::Basic
+        }
+    }
+}
+
+impl Color {
+    ///Converts the color to the closest color that can be displayed with `depth`
+    pub(crate) fn downgrade(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (Self::Rgb(r, g, b), ColorDepth::Ansi256) => Self::Ansi256(rgb_to_256(r, g, b)),
+            (Self::Rgb(r, g, b), ColorDepth::Basic) => nearest_basic(r, g, b),
+            (Self::Ansi256(n), ColorDepth::Basic) => match BASIC.get(n as usize) {
+                Some(color) => *color,
+                None => {
+                    let (r, g, b) = ansi256_to_rgb(n);
+                    nearest_basic(r, g, b)
+                }
+            },
+            _ => self,
+        }
+    }
+}
+
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    //Grays have a more precise ramp
+    if r == g && g == b {
+        return match r {
+            0..8 => 16,
+            249.. => 231,
+            _ => 232 + ((u16::from(r) - 8) * 24 / 241) as u8,
+        };
+    }
+
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| CUBE_LEVELS[*i].abs_diff(c))
+            .unwrap_or_default() as u8
+    };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+fn ansi256_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..16 => BASIC_RGB[n as usize],
+        16..232 => {
+            let n = n - 16;
+            (
+                CUBE_LEVELS[(n / 36) as usize],
+                CUBE_LEVELS[(n / 6 % 6) as usize],
+                CUBE_LEVELS[(n % 6) as usize],
+            )
+        }
+        _ => {
+            let gray = 8 + (n - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn nearest_basic(r: u8, g: u8, b: u8) -> Color {
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+
+    let index = (0..BASIC_RGB.len())
+        .min_by_key(|i| distance(BASIC_RGB[*i]))
+        .unwrap_or_default();
+    BASIC[index]
+}
+
+///Escape sequence that sets the foreground color, empty for the default color
+pub(crate) struct Fg(pub(crate) Color, pub(crate) ColorDepth);
+
+impl fmt::Display for Fg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.downgrade(self.1) {
+            Color::Default => Ok(()),
+            Color::Ansi256(n) => write!(f, "\x1b[38;5;{n}m"),
+            Color::Rgb(r, g, b) => write!(f, "\x1b[38;2;{r};{g};{b}m"),
+            color => {
+                let index = BASIC.iter().position(|c| *c == color).unwrap_or_default();
+                let code = if index < 8 { 30 + index } else { 82 + index };
+                write!(f, "\x1b[{code}m")
+            }
+        }
+    }
+}
+
+///Colors of the parts of a record in the console output
+///
+///Example:
+///
+///```
+///use lunar_logger::{Builder, Color, Theme};
+///
+///Builder::new()
+///    .theme(Theme {
+///        info: Color::Blue,
+///        brackets: Color::Default,
+///        ..Theme::default()
+///    })
+///    .init()
+///    .unwrap();
+///```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    ///Color of the `ERROR` level
+    pub error: Color,
+    ///Color of the `WARN` level
+    pub warn: Color,
+    ///Color of the `INFO` level
+    pub info: Color,
+    ///Color of the `DEBUG` level
+    pub debug: Color,
+    ///Color of the `TRACE` level
+    pub trace: Color,
+    ///Color of the time stamp
+    pub timestamp: Color,
+    ///Color of the target
+    pub target: Color,
+    ///Color of the brackets around the time stamp, level and target
+    pub brackets: Color,
+}
+
+impl Theme {
+    ///Creates the default theme
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            error: Color::Red,
+            warn: Color::Yellow,
+            info: Color::Green,
+            debug: Color::Magenta,
+            trace: Color::Cyan,
+            timestamp: Color::Default,
+            target: Color::Default,
+            brackets: Color::BrightBlack,
+        }
+    }
+
+    ///Color of the `level`
+    #[must_use]
+    pub const fn level(&self, level: log::Level) -> Color {
+        match level {
+            log::Level::Error => self.error,
+            log::Level::Warn => self.warn,
+            log::Level::Info => self.info,
+            log::Level::Debug => self.debug,
+            log::Level::Trace => self.trace,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new()
+    }
+}
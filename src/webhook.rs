@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use crate::{batch::Batcher, format::json, http::Client, sink::SinkErrors, FormattedRecord, Sink};
+
+///Body of the requests sent by a [`WebhookSink`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WebhookFormat {
+    ///A JSON array of records, sent as `application/json`
+    #[default]
+    JsonArray,
+    ///One JSON record per line, sent as `application/x-ndjson`
+    Ndjson,
+}
+
+///Posts batches of records to a URL, e.g. a custom log collector
+///
+///Records are serialized as in [`Format::Json`](crate::Format::Json) and sent from a background
+///thread, once 100 records are collected or a second passes.
+///
+///Example:
+///```no_run
+///use lunar_logger::{Builder, WebhookFormat, WebhookSink};
+///
+///let webhook = WebhookSink::builder("https://collector.local/logs")
+///    .format(WebhookFormat::Ndjson)
+///    .header("X-Api-Key", "secret")
+///    .build();
+///
+///Builder::new().add_sink(Box::new(webhook)).init().unwrap();
+///```
+pub struct WebhookSink {
+    batcher: Batcher<String>,
+    errors: SinkErrors,
+}
+
+///Builder of a [`WebhookSink`]
+pub struct WebhookSinkBuilder {
+    client: Client,
+    format: WebhookFormat,
+    max_batch: usize,
+    interval: Duration,
+}
+
+impl WebhookSink {
+    ///Starts building a sink that posts to `url`
+    #[must_use]
+    pub fn builder(url: &str) -> WebhookSinkBuilder {
+        WebhookSinkBuilder {
+            client: Client::new(url),
+            format: WebhookFormat::JsonArray,
+            max_batch: 100,
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl WebhookSinkBuilder {
+    ///Sets the body of the requests, a JSON array by default
+    #[must_use]
+    pub const fn format(mut self, format: WebhookFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    ///Authenticates with HTTP basic authentication
+    #[must_use]
+    pub fn basic_auth(mut self, user: &str, password: &str) -> Self {
+        self.client.basic_auth(user, password);
+        self
+    }
+
+    ///Adds a header to the requests, e.g. an API key
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.client.header(name, value);
+        self
+    }
+
+    ///Sets the number of records that are sent at once, 100 by default
+    #[must_use]
+    pub const fn max_batch(mut self, records: usize) -> Self {
+        self.max_batch = records;
+        self
+    }
+
+    ///Sets how long records wait for a batch to fill before they are sent, a second by default
+    #[must_use]
+    pub const fn flush_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    ///Starts the background thread and creates the sink
+    #[must_use]
+    pub fn build(self) -> WebhookSink {
+        let client = self.client;
+        let errors = client.errors.clone();
+        let format = self.format;
+        WebhookSink {
+            batcher: Batcher::new(self.max_batch, self.interval, move |records| {
+                let (content_type, body) = body(format, &records);
+                client.post(content_type, body.as_bytes());
+            }),
+            errors,
+        }
+    }
+}
+
+///Content type and body of a request with the records
+pub(crate) fn body(format: WebhookFormat, records: &[String]) -> (&'static str, String) {
+    match format {
+        WebhookFormat::JsonArray => ("application/json", format!("[{}]", records.join(","))),
+        WebhookFormat::Ndjson => {
+            let mut body = records.join("\n");
+            body.push('\n');
+            ("application/x-ndjson", body)
+        }
+    }
+}
+
+impl Sink for WebhookSink {
+    fn write(&self, record: &FormattedRecord) {
+        let mut json = json(record);
+        json.truncate(json.trim_end_matches('\n').len());
+        self.batcher.push(json);
+    }
+
+    fn flush(&self) {
+        self.batcher.flush();
+    }
+
+    fn set_error_handler(&self, handler: Option<crate::SharedErrorHandler>) {
+        self.errors.set(handler);
+    }
+}